@@ -0,0 +1,36 @@
+//! Times `(map f (range 0 n))` for growing `n`. Run with `cargo bench`.
+//!
+//! Lists are persistent, so building the result one `append` at a time
+//! costs the same per element however long the list gets: the time per
+//! element printed for each size should stay roughly flat.
+
+use std::time::{Duration, Instant};
+use stutter::Interpreter;
+
+const SIZES: &[usize] = &[12_500, 25_000, 50_000, 100_000];
+const RUNS: u32 = 3;
+
+fn time_map(interpreter: &mut Interpreter, n: usize) -> Duration {
+    let source = format!("(len (map (lambda (x) (* 2 x)) (range 0 {})))", n);
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        interpreter.eval_str(&source).expect("benchmark failed");
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+fn main() {
+    let mut interpreter = Interpreter::new();
+    println!("{:>10} {:>12} {:>14}", "n", "total", "per element");
+    for &n in SIZES {
+        let elapsed = time_map(&mut interpreter, n);
+        println!(
+            "{:>10} {:>10.1}ms {:>12.0}ns",
+            n,
+            elapsed.as_secs_f64() * 1e3,
+            elapsed.as_secs_f64() * 1e9 / n as f64
+        );
+    }
+}
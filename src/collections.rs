@@ -0,0 +1,247 @@
+// This file is part of Stutter.
+//
+// Stutter is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Stutter is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Stutter.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Builtins for working with maps and sets.
+
+use crate::error::{ErrorKind, StutterError};
+use crate::eval::expect_args;
+use crate::object::{sorted_entries, MapKey, StutterObject};
+use crate::parser::{op_to_string, Op};
+use rpds::{HashTrieMap, HashTrieSet};
+
+pub(crate) type Map = HashTrieMap<MapKey, StutterObject>;
+pub(crate) type Set = HashTrieSet<MapKey>;
+
+pub(crate) fn expect_map<'a>(
+    form: &str,
+    obj: &'a StutterObject,
+) -> Result<&'a Map, StutterError> {
+    match obj {
+        StutterObject::Map(map) => Ok(map),
+        _ => Err(ErrorKind::Type(format!(
+            "type error: expected form {}, got {}",
+            form, obj
+        ))
+        .into()),
+    }
+}
+
+fn expect_set<'a>(
+    form: &str,
+    obj: &'a StutterObject,
+) -> Result<&'a Set, StutterError> {
+    match obj {
+        StutterObject::Set(set) => Ok(set),
+        _ => Err(ErrorKind::Type(format!(
+            "type error: expected form {}, got {}",
+            form, obj
+        ))
+        .into()),
+    }
+}
+
+fn expect_at_least(
+    form: &str,
+    v: &[StutterObject],
+    n: usize,
+) -> Result<(), StutterError> {
+    if v.len() < n {
+        Err(ErrorKind::Arity(format!(
+            "expecting form of {}, got {} arguments",
+            form,
+            v.len()
+        ))
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Adds alternating keys and values to `map`.
+fn assoc_pairs(
+    form: &str,
+    mut map: Map,
+    pairs: &[StutterObject],
+) -> Result<Map, StutterError> {
+    if !pairs.len().is_multiple_of(2) {
+        return Err(ErrorKind::Arity(format!(
+            "expecting form of {}, got a key without a value",
+            form
+        ))
+        .into());
+    }
+    for pair in pairs.chunks(2) {
+        map = map.insert(MapKey::from_object(&pair[0])?, pair[1].clone());
+    }
+    Ok(map)
+}
+
+pub(crate) fn eval_map_op(
+    op: &Op,
+    v: &[StutterObject],
+) -> Result<StutterObject, StutterError> {
+    match op {
+        Op::HashMap => {
+            let form = "(hash-map KEY VALUE...)";
+            Ok(StutterObject::Map(assoc_pairs(form, Map::new(), v)?))
+        }
+        Op::Get => {
+            let form = "(get MAP KEY [DEFAULT])";
+            if v.len() != 3 {
+                expect_args(form, v, 2)?;
+            }
+            let map = expect_map(form, &v[0])?;
+            let key = MapKey::from_object(&v[1])?;
+            let default = v.get(2).cloned().unwrap_or(StutterObject::Nil);
+            Ok(map.get(&key).cloned().unwrap_or(default))
+        }
+        Op::Assoc => {
+            let form = "(assoc MAP KEY VALUE...)";
+            expect_at_least(form, v, 1)?;
+            let map = expect_map(form, &v[0])?.clone();
+            Ok(StutterObject::Map(assoc_pairs(form, map, &v[1..])?))
+        }
+        Op::Dissoc => {
+            let form = "(dissoc MAP KEY...)";
+            expect_at_least(form, v, 1)?;
+            let mut map = expect_map(form, &v[0])?.clone();
+            for key in v[1..].iter() {
+                map = map.remove(&MapKey::from_object(key)?);
+            }
+            Ok(StutterObject::Map(map))
+        }
+        Op::Keys | Op::Vals => {
+            let form = if op == &Op::Keys {
+                "(keys MAP)"
+            } else {
+                "(vals MAP)"
+            };
+            expect_args(form, v, 1)?;
+            let entries = sorted_entries(expect_map(form, &v[0])?);
+            Ok(StutterObject::List(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| match op {
+                        Op::Keys => key.to_object(),
+                        _ => value.clone(),
+                    })
+                    .collect(),
+            ))
+        }
+        Op::HasKey => {
+            let form = "(contains? MAP KEY)";
+            expect_args(form, v, 2)?;
+            let key = MapKey::from_object(&v[1])?;
+            match &v[0] {
+                StutterObject::Set(set) => {
+                    Ok(StutterObject::Bool(set.contains(&key)))
+                }
+                map => Ok(StutterObject::Bool(
+                    expect_map(form, map)?.contains_key(&key),
+                )),
+            }
+        }
+        Op::Merge => {
+            let form = "(merge MAP...)";
+            expect_at_least(form, v, 1)?;
+            let mut merged = expect_map(form, &v[0])?.clone();
+            for other in v[1..].iter() {
+                for (key, value) in expect_map(form, other)?.iter() {
+                    merged = merged.insert(key.clone(), value.clone());
+                }
+            }
+            Ok(StutterObject::Map(merged))
+        }
+        _ => Err(ErrorKind::Syntax(format!(
+            "{:?} is not a map operation",
+            op
+        ))
+        .into()),
+    }
+}
+
+fn set_of<'a>(
+    items: impl Iterator<Item = &'a StutterObject>,
+) -> Result<Set, StutterError> {
+    items.map(MapKey::from_object).collect()
+}
+
+pub(crate) fn eval_set_op(
+    op: &Op,
+    v: &[StutterObject],
+) -> Result<StutterObject, StutterError> {
+    match op {
+        Op::HashSet => Ok(StutterObject::Set(set_of(v.iter())?)),
+        Op::ToSet => {
+            let form = "(set LIST)";
+            expect_args(form, v, 1)?;
+            match &v[0] {
+                StutterObject::List(items) => {
+                    Ok(StutterObject::Set(set_of(items.iter())?))
+                }
+                StutterObject::Nil => Ok(StutterObject::Set(Set::new())),
+                StutterObject::Set(set) => Ok(StutterObject::Set(set.clone())),
+                _ => Err(ErrorKind::Type(format!(
+                    "type error: expected form {}, got {}",
+                    form, v[0]
+                ))
+                .into()),
+            }
+        }
+        Op::Union | Op::Intersection | Op::Difference => {
+            let form = format!("({} SET...)", op_to_string(op));
+            if v.is_empty() && op == &Op::Union {
+                return Ok(StutterObject::Set(Set::new()));
+            }
+            expect_at_least(&form, v, 1)?;
+            let mut result = expect_set(&form, &v[0])?.clone();
+            for other in v[1..].iter() {
+                let other = expect_set(&form, other)?;
+                result = match op {
+                    Op::Union => other
+                        .iter()
+                        .fold(result, |acc, m| acc.insert(m.clone())),
+                    Op::Intersection => result
+                        .iter()
+                        .filter(|m| other.contains(*m))
+                        .cloned()
+                        .collect(),
+                    _ => other.iter().fold(result, |acc, m| acc.remove(m)),
+                };
+            }
+            Ok(StutterObject::Set(result))
+        }
+        Op::IsSubset => {
+            let form = "(subset? SET SET)";
+            expect_args(form, v, 2)?;
+            let sub = expect_set(form, &v[0])?;
+            let sup = expect_set(form, &v[1])?;
+            Ok(StutterObject::Bool(sub.is_subset(sup)))
+        }
+        Op::IsMember => {
+            let form = "(member? ITEM SET)";
+            expect_args(form, v, 2)?;
+            let set = expect_set(form, &v[1])?;
+            Ok(StutterObject::Bool(
+                set.contains(&MapKey::from_object(&v[0])?),
+            ))
+        }
+        _ => Err(ErrorKind::Syntax(format!(
+            "{:?} is not a set operation",
+            op
+        ))
+        .into()),
+    }
+}
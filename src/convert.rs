@@ -0,0 +1,169 @@
+// This file is part of Stutter.
+//
+// Stutter is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Stutter is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Stutter.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Conversions between Stutter values and Rust types, for host programs
+//! that pass data in and out of an `Interpreter`.
+
+use crate::error::{ErrorKind, StutterError};
+use crate::object::{
+    bigint_to_f64, bigint_to_i64, ratio_to_f64, rational_to_object,
+    StutterObject,
+};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+/// Types that can be handed to Stutter as a value.
+pub trait IntoStutter {
+    fn into_stutter(self) -> StutterObject;
+}
+
+/// Types that can be read back out of a Stutter value.
+pub trait FromStutter: Sized {
+    fn from_stutter(obj: &StutterObject) -> Result<Self, StutterError>;
+}
+
+fn expected(what: &str, obj: &StutterObject) -> StutterError {
+    ErrorKind::Type(format!("expected {}, got {:?}", what, obj)).into()
+}
+
+impl IntoStutter for StutterObject {
+    fn into_stutter(self) -> StutterObject {
+        self
+    }
+}
+
+impl FromStutter for StutterObject {
+    fn from_stutter(obj: &StutterObject) -> Result<Self, StutterError> {
+        Ok(obj.clone())
+    }
+}
+
+impl IntoStutter for i64 {
+    fn into_stutter(self) -> StutterObject {
+        StutterObject::Int(BigInt::from(self))
+    }
+}
+
+impl FromStutter for i64 {
+    fn from_stutter(obj: &StutterObject) -> Result<Self, StutterError> {
+        match obj {
+            StutterObject::Int(n) => bigint_to_i64(n),
+            _ => Err(expected("Int", obj)),
+        }
+    }
+}
+
+impl IntoStutter for BigInt {
+    fn into_stutter(self) -> StutterObject {
+        StutterObject::Int(self)
+    }
+}
+
+impl FromStutter for BigInt {
+    fn from_stutter(obj: &StutterObject) -> Result<Self, StutterError> {
+        match obj {
+            StutterObject::Int(n) => Ok(n.clone()),
+            _ => Err(expected("Int", obj)),
+        }
+    }
+}
+
+impl IntoStutter for f64 {
+    fn into_stutter(self) -> StutterObject {
+        StutterObject::Real(self)
+    }
+}
+
+/// Integers are accepted too, the same way arithmetic mixes them with
+/// reals.
+impl FromStutter for f64 {
+    fn from_stutter(obj: &StutterObject) -> Result<Self, StutterError> {
+        match obj {
+            StutterObject::Real(r) => Ok(*r),
+            StutterObject::Int(n) => bigint_to_f64(n),
+            StutterObject::Ratio(r) => ratio_to_f64(r),
+            _ => Err(expected("Real", obj)),
+        }
+    }
+}
+
+/// Whole numbers come back as `Int`, the same way `/` gives them.
+impl IntoStutter for BigRational {
+    fn into_stutter(self) -> StutterObject {
+        rational_to_object(self)
+    }
+}
+
+impl FromStutter for BigRational {
+    fn from_stutter(obj: &StutterObject) -> Result<Self, StutterError> {
+        match obj {
+            StutterObject::Ratio(r) => Ok(r.clone()),
+            StutterObject::Int(n) => Ok(BigRational::from_integer(n.clone())),
+            _ => Err(expected("Ratio", obj)),
+        }
+    }
+}
+
+impl IntoStutter for bool {
+    fn into_stutter(self) -> StutterObject {
+        StutterObject::Bool(self)
+    }
+}
+
+impl FromStutter for bool {
+    fn from_stutter(obj: &StutterObject) -> Result<Self, StutterError> {
+        match obj {
+            StutterObject::Bool(b) => Ok(*b),
+            _ => Err(expected("Bool", obj)),
+        }
+    }
+}
+
+impl IntoStutter for String {
+    fn into_stutter(self) -> StutterObject {
+        StutterObject::Str(self)
+    }
+}
+
+impl IntoStutter for &str {
+    fn into_stutter(self) -> StutterObject {
+        StutterObject::Str(self.to_string())
+    }
+}
+
+impl FromStutter for String {
+    fn from_stutter(obj: &StutterObject) -> Result<Self, StutterError> {
+        match obj {
+            StutterObject::Str(s) => Ok(s.clone()),
+            _ => Err(expected("Str", obj)),
+        }
+    }
+}
+
+impl<T: IntoStutter> IntoStutter for Vec<T> {
+    fn into_stutter(self) -> StutterObject {
+        StutterObject::List(self.into_iter().map(T::into_stutter).collect())
+    }
+}
+
+impl<T: FromStutter> FromStutter for Vec<T> {
+    fn from_stutter(obj: &StutterObject) -> Result<Self, StutterError> {
+        match obj {
+            StutterObject::List(l) => l.iter().map(T::from_stutter).collect(),
+            StutterObject::Nil => Ok(Vec::new()),
+            _ => Err(expected("List", obj)),
+        }
+    }
+}
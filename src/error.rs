@@ -0,0 +1,202 @@
+// This file is part of Stutter.
+//
+// Stutter is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Stutter is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Stutter.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Source locations and the errors the interpreter reports against them.
+
+use crate::eval::MAX_EVAL_DEPTH;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+/// A named piece of Stutter source text, such as a file or a command typed
+/// into the REPL. Spans point back into it so that errors can quote the
+/// offending line.
+#[derive(Debug, Default, PartialEq)]
+pub struct Source {
+    pub name: String,
+    pub text: String,
+}
+
+/// A 1-based line and column within a `Source`.
+#[derive(Clone, Default, PartialEq)]
+pub struct Span {
+    pub(crate) source: Rc<Source>,
+    pub(crate) line: usize,
+    pub(crate) col: usize,
+}
+
+impl Span {
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.source.name, self.line, self.col)
+    }
+}
+
+/// What went wrong, independent of where. `Display` renders the message the
+/// interpreter has always printed for each kind of failure.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    Lex(String),
+    Parse(String),
+    Syntax(String),
+    Unbound(String),
+    Type(String),
+    Arity(String),
+    IndexOutOfRange(String),
+    Value(String),
+    Io(String),
+    DivisionByZero,
+    RecursionLimit,
+    UserRaised(String),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::Unbound(name) => write!(f, "'{}' not in scope", name),
+            ErrorKind::DivisionByZero => write!(f, "division by zero"),
+            ErrorKind::RecursionLimit => write!(
+                f,
+                "maximum recursion depth of {} exceeded",
+                MAX_EVAL_DEPTH
+            ),
+            ErrorKind::Lex(msg)
+            | ErrorKind::Parse(msg)
+            | ErrorKind::Syntax(msg)
+            | ErrorKind::Type(msg)
+            | ErrorKind::Arity(msg)
+            | ErrorKind::IndexOutOfRange(msg)
+            | ErrorKind::Value(msg)
+            | ErrorKind::Io(msg)
+            | ErrorKind::UserRaised(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// An error together with the span it was raised at and the names of the
+/// lambdas it unwound through, innermost first. Tail calls replace their
+/// caller's frame, so only the last of a chain of tail calls is recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StutterError {
+    pub(crate) kind: ErrorKind,
+    pub(crate) span: Option<Span>,
+    pub(crate) backtrace: Vec<String>,
+}
+
+impl StutterError {
+    pub(crate) fn new(kind: ErrorKind, span: &Span) -> StutterError {
+        StutterError {
+            kind,
+            span: Some(span.clone()),
+            backtrace: Vec::new(),
+        }
+    }
+
+    /// Attaches `span` unless the error already points somewhere more
+    /// specific.
+    pub(crate) fn at(mut self, span: &Span) -> StutterError {
+        if self.span.is_none() {
+            self.span = Some(span.clone());
+        }
+        self
+    }
+
+    pub(crate) fn in_frame(mut self, name: &str) -> StutterError {
+        self.backtrace.push(name.to_string());
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
+    }
+
+    /// Names of the lambdas the error unwound through, innermost first.
+    pub fn backtrace(&self) -> &[String] {
+        &self.backtrace
+    }
+
+    /// Formats the error as `file:line:col: error: message`, followed by
+    /// the source line, a caret under the offending column and the
+    /// backtrace, with runs of the same frame collapsed into one line.
+    pub fn render(&self) -> String {
+        let mut rendered = match &self.span {
+            Some(span) if span.line > 0 => {
+                let mut located = format!(
+                    "{}:{}:{}: error: {}",
+                    span.source.name, span.line, span.col, self.kind
+                );
+                let line = span.source.text.lines().nth(span.line - 1);
+                if let Some(line) = line {
+                    let indent: String = line
+                        .chars()
+                        .take(span.col - 1)
+                        .map(|c| if c == '\t' { '\t' } else { ' ' })
+                        .collect();
+                    located.push_str(&format!("\n{}\n{}^", line, indent));
+                }
+                located
+            }
+            _ => format!("error: {}", self.kind),
+        };
+        let mut frames = self.backtrace.iter().peekable();
+        while let Some(name) = frames.next() {
+            let mut repeats = 1;
+            while frames.peek() == Some(&name) {
+                frames.next();
+                repeats += 1;
+            }
+            rendered.push_str(&format!("\n  in {}", name));
+            if repeats > 1 {
+                rendered.push_str(&format!(" ({} times)", repeats));
+            }
+        }
+        rendered
+    }
+}
+
+impl From<ErrorKind> for StutterError {
+    fn from(kind: ErrorKind) -> StutterError {
+        StutterError {
+            kind,
+            span: None,
+            backtrace: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for StutterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Error for StutterError {}
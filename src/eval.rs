@@ -0,0 +1,2168 @@
+// This file is part of Stutter.
+//
+// Stutter is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Stutter is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Stutter.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Evaluation of parse trees, including every builtin operator.
+
+use crate::collections::{eval_map_op, eval_set_op, expect_map};
+use crate::error::{ErrorKind, Source, Span, StutterError};
+use crate::interpreter::Truthiness;
+use crate::lexer::{lex, Token};
+use crate::macros::{
+    call_macro, data_to_tree, gensym, macroexpand, quasiquote, tree_to_data,
+};
+use crate::object::{
+    bigint_to_f64, bigint_to_i64, bigint_to_usize, f64_to_bigint,
+    i64_to_bigint, name_lambda, ratio_to_f64, rational_to_object,
+    sorted_members, usize_to_bigint, Closure, MapKey, Params, RecursiveGroup,
+    StutterObject,
+};
+use crate::parser::{op_to_string, parse, token_to_op, Op, ParseTree};
+use im_rc::Vector;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::pow;
+use num_traits::{Signed, Zero};
+use rpds::HashTrieMap;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+fn token_to_stutterobject(tok: &Token) -> Result<StutterObject, StutterError> {
+    match tok {
+        Token::Id(s) => Ok(StutterObject::Id(s.to_string())),
+        Token::Int(i) => Ok(StutterObject::Int(i.clone())),
+        Token::Real(f) => Ok(StutterObject::Real(*f)),
+        Token::Ratio(r) => Ok(StutterObject::Ratio(r.clone())),
+        Token::Bool(b) => Ok(StutterObject::Bool(*b)),
+        Token::Nil => Ok(StutterObject::Nil),
+        Token::Str(s) => Ok(StutterObject::Str(s.to_string())),
+        Token::Keyword(k) => Ok(StutterObject::Keyword(k.to_string())),
+        Token::Lparen
+        | Token::Rparen
+        | Token::Let
+        | Token::Def
+        | Token::Quote
+        | Token::If
+        | Token::Defmacro
+        | Token::Quasiquote
+        | Token::Unquote
+        | Token::UnquoteSplicing
+        | Token::Apostrophe
+        | Token::And
+        | Token::Or
+        | Token::Cond
+        | Token::When
+        | Token::Unless
+        | Token::Case
+        | Token::Begin
+        | Token::Letrec
+        | Token::Backquote
+        | Token::Comma
+        | Token::CommaAt => Err(ErrorKind::Syntax(format!(
+            "token: {:?} does not form a valid atom",
+            tok
+        ))
+        .into()),
+        _ => Ok(StutterObject::Builtin(token_to_op(tok)?)),
+    }
+}
+
+fn lookup_global_env(
+    variable_name: &String,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    match global_env.get(variable_name) {
+        Some(value) => Ok(value.clone()),
+        None => Err(ErrorKind::Unbound(variable_name.to_string()).into()),
+    }
+}
+
+fn lookup_env(
+    obj: &StutterObject,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    match obj {
+        StutterObject::Id(variable_name) => match env.get(variable_name) {
+            Some(value) => Ok(value.clone()),
+            None => lookup_global_env(variable_name, global_env),
+        },
+        _ => Ok(obj.clone()),
+    }
+}
+
+fn lookup_env_string(
+    name: &String,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    let res = env.get(name);
+    match res {
+        Some(x) => Ok(x.clone()),
+        _ => match global_env.get(name) {
+            Some(y) => Ok(y.clone()),
+            _ => Err(ErrorKind::Unbound(name.to_string()).into()),
+        },
+    }
+}
+
+fn apply_op(
+    op: &Op,
+    acc: &StutterObject,
+    operand: &StutterObject,
+) -> Result<StutterObject, StutterError> {
+    match (acc, operand) {
+        (StutterObject::Int(n1), StutterObject::Int(n2)) => match op {
+            Op::Add => Ok(StutterObject::Int(n1 + n2)),
+            Op::Sub => Ok(StutterObject::Int(n1 - n2)),
+            Op::Div | Op::IntDiv | Op::Mod if n2.is_zero() => {
+                Err(ErrorKind::DivisionByZero.into())
+            }
+            Op::Div => Ok(rational_to_object(BigRational::new(
+                n1.clone(),
+                n2.clone(),
+            ))),
+            Op::IntDiv => Ok(StutterObject::Int(n1 / n2)),
+            Op::Mod => Ok(StutterObject::Int(n1 % n2)),
+            Op::Pow if n2.is_negative() => apply_ratio_op(
+                op,
+                BigRational::from_integer(n1.clone()),
+                BigRational::from_integer(n2.clone()),
+            ),
+            Op::Pow => {
+                Ok(StutterObject::Int(pow(n1.clone(), bigint_to_usize(n2)?)))
+            }
+            Op::Mul => Ok(StutterObject::Int(n1 * n2)),
+            Op::Gt => Ok(StutterObject::Bool(n1 > n2)),
+            Op::Lt => Ok(StutterObject::Bool(n1 < n2)),
+            Op::Eq => Ok(StutterObject::Bool(n1 == n2)),
+            Op::Gte => Ok(StutterObject::Bool(n1 >= n2)),
+            Op::Lte => Ok(StutterObject::Bool(n1 <= n2)),
+            _ => {
+                Err(ErrorKind::Type(format!("{:?} not implemented", op))
+                    .into())
+            }
+        },
+        (&StutterObject::Real(f1), &StutterObject::Real(f2)) => {
+            match op {
+                Op::Add => Ok(StutterObject::Real(f1 + f2)),
+                Op::Sub => Ok(StutterObject::Real(f1 - f2)),
+                Op::Div => Ok(StutterObject::Real(f1 / f2)),
+                Op::Mod => Ok(StutterObject::Real(f1 % f2)),
+                Op::Pow => Ok(StutterObject::Real(f1.powf(f2))),
+                Op::Mul => Ok(StutterObject::Real(f1 * f2)),
+                Op::Gt => Ok(StutterObject::Bool(f1 > f2)),
+                Op::Lt => Ok(StutterObject::Bool(f1 < f2)),
+                Op::Eq => Ok(StutterObject::Bool(f1 == f2)),
+                Op::Gte => Ok(StutterObject::Bool(f1 >= f2)),
+                Op::Lte => Ok(StutterObject::Bool(f1 <= f2)),
+                _ => Err(ErrorKind::Type(format!("{:?} not implemented", op))
+                    .into()),
+            }
+        }
+        (StutterObject::Ratio(r1), StutterObject::Ratio(r2)) => {
+            apply_ratio_op(op, r1.clone(), r2.clone())
+        }
+        (StutterObject::Ratio(r1), StutterObject::Int(n2)) => apply_ratio_op(
+            op,
+            r1.clone(),
+            BigRational::from_integer(n2.clone()),
+        ),
+        (StutterObject::Int(n1), StutterObject::Ratio(r2)) => apply_ratio_op(
+            op,
+            BigRational::from_integer(n1.clone()),
+            r2.clone(),
+        ),
+        (StutterObject::Ratio(r), StutterObject::Real(_)) => {
+            apply_op(op, &StutterObject::Real(ratio_to_f64(r)?), operand)
+        }
+        (StutterObject::Real(_), StutterObject::Ratio(r)) => {
+            apply_op(op, acc, &StutterObject::Real(ratio_to_f64(r)?))
+        }
+        (StutterObject::Int(n1), &StutterObject::Real(f2)) => match op {
+            Op::Add => Ok(StutterObject::Real((bigint_to_f64(n1)?) + f2)),
+            Op::Sub => Ok(StutterObject::Real((bigint_to_f64(n1)?) - f2)),
+            Op::Div => Ok(StutterObject::Real((bigint_to_f64(n1)?) / f2)),
+            Op::Mod => Ok(StutterObject::Real((bigint_to_f64(n1)?) % f2)),
+            Op::Pow => Ok(StutterObject::Real((bigint_to_f64(n1)?).powf(f2))),
+            Op::Mul => Ok(StutterObject::Real((bigint_to_f64(n1)?) * f2)),
+            Op::Gt => Ok(StutterObject::Bool((bigint_to_f64(n1)?) > f2)),
+            Op::Lt => Ok(StutterObject::Bool((bigint_to_f64(n1)?) < f2)),
+            Op::Eq => Ok(StutterObject::Bool((bigint_to_f64(n1)?) == f2)),
+            Op::Gte => Ok(StutterObject::Bool((bigint_to_f64(n1)?) >= f2)),
+            Op::Lte => Ok(StutterObject::Bool((bigint_to_f64(n1)?) <= f2)),
+            _ => {
+                Err(ErrorKind::Type(format!("{:?} not implemented", op))
+                    .into())
+            }
+        },
+        (&StutterObject::Real(f1), StutterObject::Int(n2)) => match op {
+            Op::Add => Ok(StutterObject::Real(f1 + (bigint_to_f64(n2)?))),
+            Op::Sub => Ok(StutterObject::Real(f1 - (bigint_to_f64(n2)?))),
+            Op::Div => Ok(StutterObject::Real(f1 / (bigint_to_f64(n2)?))),
+            Op::Mod => Ok(StutterObject::Real(f1 % (bigint_to_f64(n2)?))),
+            Op::Pow => Ok(StutterObject::Real(f1.powf(bigint_to_f64(n2)?))),
+            Op::Mul => Ok(StutterObject::Real(f1 * (bigint_to_f64(n2)?))),
+            Op::Gt => Ok(StutterObject::Bool(f1 > (bigint_to_f64(n2)?))),
+            Op::Lt => Ok(StutterObject::Bool(f1 < (bigint_to_f64(n2)?))),
+            Op::Eq => Ok(StutterObject::Bool(f1 == (bigint_to_f64(n2)?))),
+            Op::Gte => Ok(StutterObject::Bool(f1 >= (bigint_to_f64(n2)?))),
+            Op::Lte => Ok(StutterObject::Bool(f1 <= (bigint_to_f64(n2)?))),
+            _ => {
+                Err(ErrorKind::Type(format!("{:?} not implemented", op))
+                    .into())
+            }
+        },
+        (StutterObject::Keyword(s1), StutterObject::Keyword(s2))
+        | (StutterObject::Symbol(s1), StutterObject::Symbol(s2)) => match op {
+            Op::Eq => Ok(StutterObject::Bool(s1 == s2)),
+            _ => Err(ErrorKind::Type(format!(
+                "{:?} not implemented for symbols",
+                op
+            ))
+            .into()),
+        },
+        (StutterObject::Nil, StutterObject::Nil) => match op {
+            Op::Eq => Ok(StutterObject::Bool(true)),
+            _ => Err(ErrorKind::Type(format!(
+                "{:?} not implemented for Nil",
+                op
+            ))
+            .into()),
+        },
+        (StutterObject::Bool(b1), StutterObject::Bool(b2)) => match op {
+            Op::Eq => Ok(StutterObject::Bool(b1 == b2)),
+            _ => Err(ErrorKind::Type(format!(
+                "{:?} not implemented for booleans",
+                op
+            ))
+            .into()),
+        },
+        (StutterObject::List(l1), StutterObject::List(l2)) => match op {
+            Op::Eq => Ok(StutterObject::Bool(l1 == l2)),
+            _ => Err(ErrorKind::Type(format!(
+                "{:?} not implemented for lists",
+                op
+            ))
+            .into()),
+        },
+        (StutterObject::Nil, StutterObject::List(l))
+        | (StutterObject::List(l), StutterObject::Nil) => match op {
+            Op::Eq => Ok(StutterObject::Bool(l.is_empty())),
+            _ => Err(ErrorKind::Type(format!(
+                "{:?} not implemented for lists",
+                op
+            ))
+            .into()),
+        },
+        (StutterObject::Map(m1), StutterObject::Map(m2)) => match op {
+            Op::Eq => Ok(StutterObject::Bool(m1 == m2)),
+            _ => Err(ErrorKind::Type(format!(
+                "{:?} not implemented for maps",
+                op
+            ))
+            .into()),
+        },
+        (StutterObject::Set(s1), StutterObject::Set(s2)) => match op {
+            Op::Eq => Ok(StutterObject::Bool(s1 == s2)),
+            _ => Err(ErrorKind::Type(format!(
+                "{:?} not implemented for sets",
+                op
+            ))
+            .into()),
+        },
+        (StutterObject::Str(s1), StutterObject::Str(s2)) => match op {
+            Op::Gt => Ok(StutterObject::Bool(s1 > s2)),
+            Op::Lt => Ok(StutterObject::Bool(s1 < s2)),
+            Op::Eq => Ok(StutterObject::Bool(s1 == s2)),
+            Op::Gte => Ok(StutterObject::Bool(s1 >= s2)),
+            Op::Lte => Ok(StutterObject::Bool(s1 <= s2)),
+            _ => Err(ErrorKind::Type(format!(
+                "{:?} not implemented for strings",
+                op
+            ))
+            .into()),
+        },
+        _ => {
+            let msg = format!(
+                "incompatible types: ({:?} {:?} {:?}) not supported",
+                op, acc, operand
+            );
+            Err(ErrorKind::Type(msg).into())
+        }
+    }
+}
+
+fn apply_ratio_op(
+    op: &Op,
+    r1: BigRational,
+    r2: BigRational,
+) -> Result<StutterObject, StutterError> {
+    match op {
+        Op::Div | Op::IntDiv | Op::Mod if r2.is_zero() => {
+            Err(ErrorKind::DivisionByZero.into())
+        }
+        Op::Add => Ok(rational_to_object(r1 + r2)),
+        Op::Sub => Ok(rational_to_object(r1 - r2)),
+        Op::Mul => Ok(rational_to_object(r1 * r2)),
+        Op::Div => Ok(rational_to_object(r1 / r2)),
+        Op::IntDiv => Ok(StutterObject::Int((r1 / r2).trunc().to_integer())),
+        Op::Mod => Ok(rational_to_object(r1 % r2)),
+        Op::Pow if r2.is_integer() && r2.is_negative() && r1.is_zero() => {
+            Err(ErrorKind::DivisionByZero.into())
+        }
+        Op::Pow if r2.is_integer() => {
+            // A negative power is the reciprocal of the positive one.
+            let exponent = bigint_to_usize(&r2.to_integer().abs())?;
+            let base = if r2.is_negative() { r1.recip() } else { r1 };
+            Ok(rational_to_object(pow(base, exponent)))
+        }
+        Op::Pow => Ok(StutterObject::Real(
+            ratio_to_f64(&r1)?.powf(ratio_to_f64(&r2)?),
+        )),
+        Op::Gt => Ok(StutterObject::Bool(r1 > r2)),
+        Op::Lt => Ok(StutterObject::Bool(r1 < r2)),
+        Op::Eq => Ok(StutterObject::Bool(r1 == r2)),
+        Op::Gte => Ok(StutterObject::Bool(r1 >= r2)),
+        Op::Lte => Ok(StutterObject::Bool(r1 <= r2)),
+        _ => Err(ErrorKind::Type(format!("{:?} not implemented", op)).into()),
+    }
+}
+
+fn reduce(
+    op: &Op,
+    list: &[StutterObject],
+) -> Result<StutterObject, StutterError> {
+    let (first, rest) = match list.split_first() {
+        Some(split) => split,
+        None => {
+            return Err(ErrorKind::Arity(format!(
+                "{} expecting at least 1 argument, got 0",
+                op_to_string(op)
+            ))
+            .into())
+        }
+    };
+    let mut acc = first.clone();
+    for operand in rest.iter() {
+        acc = apply_op(op, &acc, operand)?;
+    }
+    Ok(acc)
+}
+
+/// The outcome of evaluating one step of an expression. Forms whose result
+/// is the value of another expression in tail position (`if` branches, `let`
+/// bodies and lambda bodies) hand that expression back to `eval` instead of
+/// recursing, so tail calls run in constant Rust stack space. A macro call
+/// hands back the code it expanded to in the same way.
+enum Step<'t> {
+    Done(StutterObject),
+    Eval(&'t ParseTree, HashTrieMap<String, StutterObject>),
+    Call(Rc<Closure>, HashTrieMap<String, StutterObject>),
+    Expand(Rc<ParseTree>, HashTrieMap<String, StutterObject>),
+}
+
+fn apply_func<'t>(
+    func: StutterObject,
+    xs: &[ParseTree],
+    span: &Span,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    match &func {
+        StutterObject::Macro(closure) => {
+            let tree = expand_macro(closure, xs, span, global_env)?;
+            Ok(Step::Expand(Rc::new(tree), env.clone()))
+        }
+        StutterObject::Lambda(_)
+        | StutterObject::Builtin(_)
+        | StutterObject::Native(_) => {
+            // TODO: multithread this
+            let args = resolve_exprs(xs, env, global_env)?;
+            apply_values(func, args, span, global_env)
+        }
+        // `(x)` of anything else is just its value, as it always has been.
+        _ if xs.is_empty() => Ok(Step::Done(func)),
+        _ => {
+            Err(ErrorKind::Type(format!("{} is not a function", func)).into())
+        }
+    }
+}
+
+/// Calls `func` with evaluated arguments and runs it to completion, for
+/// builtins that are handed a function.
+fn call_function(
+    func: StutterObject,
+    args: Vec<StutterObject>,
+    span: &Span,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    match apply_values(func, args, span, global_env)? {
+        Step::Done(value) => Ok(value),
+        Step::Call(closure, env) => {
+            let name = closure.name.as_deref().unwrap_or("<lambda>");
+            eval(&closure.body, &env, global_env).map_err(|e| e.in_frame(name))
+        }
+        Step::Eval(tree, env) => eval(tree, &env, global_env),
+        Step::Expand(tree, env) => eval(&tree, &env, global_env),
+    }
+}
+
+/// The code a call to the macro `closure` with the argument forms `xs`
+/// expands to.
+fn expand_macro(
+    closure: &Closure,
+    xs: &[ParseTree],
+    span: &Span,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<ParseTree, StutterError> {
+    let args = xs
+        .iter()
+        .map(tree_to_data)
+        .collect::<Result<Vec<StutterObject>, StutterError>>()?;
+    let expansion = call_macro(closure, args, global_env)?;
+    data_to_tree(&expansion, span)
+}
+
+/// Calls `func` with arguments that have already been evaluated.
+fn apply_values<'t>(
+    func: StutterObject,
+    args: Vec<StutterObject>,
+    span: &Span,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    match &func {
+        StutterObject::Lambda(closure) => {
+            let mut new_env = match &closure.group {
+                Some(group) => group.bind(closure.env.clone()),
+                None => closure.env.clone(),
+            };
+            if let Some(self_name) = &closure.name {
+                new_env = new_env.insert(
+                    self_name.to_string(),
+                    StutterObject::Lambda(closure.clone()),
+                );
+            }
+            let name = closure.name.as_deref().unwrap_or("<lambda>");
+            let new_env =
+                bind_params(closure, name, new_env, args, global_env)?;
+            Ok(Step::Call(closure.clone(), new_env))
+        }
+        StutterObject::Builtin(Op::Apply) => {
+            let (func, args) = spread_args(args)?;
+            apply_values(func, args, span, global_env)
+        }
+        StutterObject::Builtin(op) => {
+            Ok(Step::Done(call_builtin(op, &args, span, global_env)?))
+        }
+        StutterObject::Native(native) => {
+            let result = native.call(&args);
+            Ok(Step::Done(result.map_err(|e| e.in_frame(&native.name))?))
+        }
+        _ => Err(ErrorKind::Type(format!(
+            "apply expecting a function, got {}",
+            func
+        ))
+        .into()),
+    }
+}
+
+/// Binds `args` to the parameters of `closure` in `env`, after checking
+/// there are as many as it takes. Defaults of optional and keyword
+/// parameters that weren't passed are evaluated with the parameters before
+/// them in scope.
+pub(crate) fn bind_params(
+    closure: &Closure,
+    name: &str,
+    mut env: HashTrieMap<String, StutterObject>,
+    mut args: Vec<StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<HashTrieMap<String, StutterObject>, StutterError> {
+    let params = &closure.params;
+    let mut keyword_args = Vec::new();
+    if !params.keys.is_empty() {
+        let first_keyword = args
+            .iter()
+            .position(|arg| matches!(arg, StutterObject::Keyword(_)));
+        if let Some(i) = first_keyword {
+            keyword_args = args.split_off(i);
+        }
+    }
+    params.arity().check(name, args.len())?;
+    let mut args = args.into_iter();
+    for (param, arg) in params.required.iter().zip(&mut args) {
+        env = env.insert(param.to_string(), arg);
+    }
+    for (param, default) in params.optional.iter() {
+        let value = match (args.next(), default) {
+            (Some(arg), _) => arg,
+            (None, Some(default)) => eval(default, &env, global_env)?,
+            (None, None) => StutterObject::Nil,
+        };
+        env = env.insert(param.to_string(), value);
+    }
+    if let Some(param) = &params.rest {
+        env =
+            env.insert(param.to_string(), StutterObject::List(args.collect()));
+    }
+    let mut given = keyword_pairs(name, params, keyword_args)?;
+    for (param, default) in params.keys.iter() {
+        let value = match (given.remove(param), default) {
+            (Some(arg), _) => arg,
+            (None, Some(default)) => eval(default, &env, global_env)?,
+            (None, None) => StutterObject::Nil,
+        };
+        env = env.insert(param.to_string(), value);
+    }
+    Ok(env)
+}
+
+/// Pairs up the `:keyword value` arguments of a call, checking that each
+/// keyword is one `params` declares and is given once.
+fn keyword_pairs(
+    name: &str,
+    params: &Params,
+    args: Vec<StutterObject>,
+) -> Result<HashMap<String, StutterObject>, StutterError> {
+    let mut given = HashMap::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let keyword = match &arg {
+            StutterObject::Keyword(keyword) => keyword.clone(),
+            _ => {
+                return Err(ErrorKind::Arity(format!(
+                    "{} expecting a keyword, got {}",
+                    name, arg
+                ))
+                .into())
+            }
+        };
+        if !params.keys.iter().any(|(param, _)| param == &keyword) {
+            return Err(ErrorKind::Value(format!(
+                "{} got unknown keyword :{}",
+                name, keyword
+            ))
+            .into());
+        }
+        let value = match args.next() {
+            Some(value) => value,
+            None => {
+                return Err(ErrorKind::Arity(format!(
+                    "{} expecting a value after :{}",
+                    name, keyword
+                ))
+                .into())
+            }
+        };
+        if given.insert(keyword.clone(), value).is_some() {
+            return Err(ErrorKind::Value(format!(
+                "{} got :{} more than once",
+                name, keyword
+            ))
+            .into());
+        }
+    }
+    Ok(given)
+}
+
+/// Splits the arguments of `(apply FUNCTION ARG... LIST)` into the
+/// function and the arguments to call it with.
+fn spread_args(
+    mut args: Vec<StutterObject>,
+) -> Result<(StutterObject, Vec<StutterObject>), StutterError> {
+    if args.len() < 2 {
+        return Err(ErrorKind::Arity(format!(
+            "expecting form of (apply FUNCTION ARG... LIST), got {} arguments",
+            args.len()
+        ))
+        .into());
+    }
+    match &args.pop() {
+        Some(StutterObject::List(items)) => args.extend(items.iter().cloned()),
+        Some(StutterObject::Nil) => (),
+        Some(StutterObject::Set(set)) => {
+            args.extend(sorted_members(set).into_iter().map(MapKey::to_object))
+        }
+        Some(last) => {
+            return Err(ErrorKind::Type(format!(
+                "apply expecting a list as its last argument, got {}",
+                last
+            ))
+            .into())
+        }
+        None => (),
+    }
+    let func = args.remove(0);
+    Ok((func, args))
+}
+
+fn eval_func<'t>(
+    name: &String,
+    xs: &[ParseTree],
+    span: &Span,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    let func = lookup_env_string(name, env, global_env)?;
+    apply_func(func, xs, span, env, global_env)
+}
+
+fn eval_call<'t>(
+    xs: &[ParseTree],
+    span: &Span,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    match xs.split_first() {
+        Some((callee, args)) => {
+            let func = eval(callee, env, global_env)?;
+            apply_func(func, args, span, env, global_env)
+        }
+        None => Err(ErrorKind::Syntax(String::from(
+            "syntax error, expecting a function",
+        ))
+        .into()),
+    }
+}
+
+/// Evaluates `let`, which binds each variable in turn with the ones before
+/// it in scope, or `letrec`, which binds them the same way but then lets
+/// every lambda among them call any of the others.
+fn eval_let<'t>(
+    op: &Op,
+    xs: &'t [ParseTree],
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    let form = format!("({} ((VAR EXPR)...) EXPR...)", op_to_string(op));
+    let (bindings, body) = match xs.split_first() {
+        Some((ParseTree::Branch(Op::Call, bindings, _), body))
+            if !body.is_empty() =>
+        {
+            (&bindings[..], body)
+        }
+        Some((ParseTree::Branch(Op::List, bindings, _), body))
+            if bindings.is_empty() && !body.is_empty() =>
+        {
+            (&bindings[..], body)
+        }
+        _ if xs.len() >= 2 => xs.split_at(xs.len() - 1),
+        _ => {
+            return Err(ErrorKind::Syntax(format!(
+                "expecting form of ({} (VAR EXPR)... EXPR) or {}",
+                op_to_string(op),
+                form
+            ))
+            .into())
+        }
+    };
+    let mut new_env = env.clone();
+    let mut group = Vec::new();
+    let mut has_lambdas = false;
+    for branch in bindings.iter() {
+        let (var, val) = match branch {
+            ParseTree::Branch(var_op, val_vec, _) => match var_op {
+                Op::Func(name) => {
+                    if val_vec.len() != 1 {
+                        Err(ErrorKind::Syntax(format!(
+                            "syntax error: {:?}",
+                            val_vec
+                        )))
+                    } else {
+                        let value = eval(&val_vec[0], &new_env, global_env)?;
+                        let value = name_lambda(name, &val_vec[0], value);
+                        Ok((name.to_string(), value))
+                    }
+                }
+                _ => Err(ErrorKind::Syntax(String::from("not a variable"))),
+            },
+            _ => Err(ErrorKind::Syntax(String::from(
+                "expecting variable assignment",
+            ))),
+        }?;
+        has_lambdas |= matches!(val, StutterObject::Lambda(_));
+        if op == &Op::Letrec {
+            group.push((var.clone(), val.clone()));
+        }
+        new_env = new_env.insert(var, val);
+    }
+    if has_lambdas && !group.is_empty() {
+        let group = Rc::new(RecursiveGroup::new(group));
+        new_env = group.bind(new_env);
+    }
+    eval_body(&form, body, &new_env, global_env)
+}
+
+fn eval_if<'t>(
+    xs: &'t [ParseTree],
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    if xs.len() != 3 {
+        Err(ErrorKind::Syntax(String::from(
+            "expecting form of (if (CONDITION) (EXPR) (EXPR))",
+        ))
+        .into())
+    } else {
+        let condition = eval(&xs[0], env, global_env)?;
+        let true_path = &xs[1];
+        let false_path = &xs[2];
+        let path = if is_true(&condition)? {
+            true_path
+        } else {
+            false_path
+        };
+        Ok(Step::Eval(path, env.clone()))
+    }
+}
+
+/// Whether `condition` counts as true to `if` and the other conditionals,
+/// under the truthiness policy of the running interpreter.
+fn is_true(condition: &StutterObject) -> Result<bool, StutterError> {
+    match (TRUTHINESS.with(Cell::get), condition) {
+        (_, StutterObject::Bool(b)) => Ok(*b),
+        (Truthiness::Lisp, StutterObject::Nil) => Ok(false),
+        (Truthiness::Lisp, _) => Ok(true),
+        (Truthiness::Strict, _) => Err(ErrorKind::Type(format!(
+            "expecting boolean expression, got {}",
+            condition
+        ))
+        .into()),
+    }
+}
+
+/// `obj` as a list, with `Nil` read as `()`.
+fn as_list(obj: &StutterObject) -> Option<Vector<StutterObject>> {
+    match obj {
+        StutterObject::List(l) => Some(l.clone()),
+        StutterObject::Nil => Some(Vector::new()),
+        _ => None,
+    }
+}
+
+/// The single argument in `v` as a list, with `Nil` read as `()`.
+fn expect_list(
+    form: &str,
+    v: &[StutterObject],
+) -> Result<Vector<StutterObject>, StutterError> {
+    expect_args(form, v, 1)?;
+    as_list(&v[0]).ok_or_else(|| {
+        ErrorKind::Type(format!(
+            "type error: expected form {}, got {}",
+            form, v[0]
+        ))
+        .into()
+    })
+}
+
+/// Evaluates a body of one or more expressions in order, handing the last
+/// back to `eval` so that it stays in tail position. A `begin` or macro
+/// call directly in the body is spliced into it, and a `def` there binds
+/// its name for the rest of the body. The lambdas defined so far can all
+/// call one another, as if bound by `letrec`.
+fn eval_body<'t>(
+    form: &str,
+    body: &'t [ParseTree],
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    if body.is_empty() {
+        return Err(ErrorKind::Syntax(format!(
+            "expecting form of {}, got an empty body",
+            form
+        ))
+        .into());
+    }
+    let mut pending: Vec<BodyExpr<'t>> =
+        body.iter().rev().map(BodyExpr::Written).collect();
+    let mut env = env.clone();
+    let mut defs = Vec::new();
+    while let Some(expr) = pending.pop() {
+        if let Some(spliced) = splice_body_expr(&expr, &env, global_env)? {
+            pending.extend(spliced.into_iter().rev());
+            continue;
+        }
+        if let ParseTree::Branch(Op::Def, xs, span) = expr.tree() {
+            let (name, value) = eval_def(xs, span, &env, global_env)
+                .map_err(|e| e.at(span))?;
+            defs.push((name.clone(), value.clone()));
+            env = if defs
+                .iter()
+                .any(|(_, value)| matches!(value, StutterObject::Lambda(_)))
+            {
+                Rc::new(RecursiveGroup::new(defs.clone())).bind(env)
+            } else {
+                env.insert(name, value)
+            };
+        } else if !pending.is_empty() {
+            eval(expr.tree(), &env, global_env)?;
+        } else {
+            return Ok(match expr {
+                BodyExpr::Written(tree) => Step::Eval(tree, env),
+                BodyExpr::Expanded(tree) => Step::Expand(tree, env),
+            });
+        }
+    }
+    // The body ended with a `def`, which like a global one gives `Nil`.
+    Ok(Step::Done(StutterObject::Nil))
+}
+
+/// An expression in a body, either as written or as a macro expanded it.
+enum BodyExpr<'t> {
+    Written(&'t ParseTree),
+    Expanded(Rc<ParseTree>),
+}
+
+impl BodyExpr<'_> {
+    fn tree(&self) -> &ParseTree {
+        match self {
+            BodyExpr::Written(tree) => tree,
+            BodyExpr::Expanded(tree) => tree,
+        }
+    }
+}
+
+/// The expressions to put in place of `expr` when it is a `begin` or a
+/// macro call, or `None` when it stays as it is.
+fn splice_body_expr<'t>(
+    expr: &BodyExpr<'t>,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Option<Vec<BodyExpr<'t>>>, StutterError> {
+    match (expr, expr.tree()) {
+        (BodyExpr::Written(ParseTree::Branch(Op::Begin, xs, _)), _) => {
+            Ok(Some(xs.iter().map(BodyExpr::Written).collect()))
+        }
+        (_, ParseTree::Branch(Op::Begin, xs, _)) => Ok(Some(
+            xs.iter()
+                .map(|x| BodyExpr::Expanded(Rc::new(x.clone())))
+                .collect(),
+        )),
+        (_, ParseTree::Branch(Op::Func(name), xs, span)) => {
+            match env.get(name).or_else(|| global_env.get(name)) {
+                Some(StutterObject::Macro(closure)) => {
+                    let closure = closure.clone();
+                    let tree = expand_macro(&closure, xs, span, global_env)
+                        .map_err(|e| e.at(span))?;
+                    Ok(Some(vec![BodyExpr::Expanded(Rc::new(tree))]))
+                }
+                _ => Ok(None),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Evaluates `begin`, or the body of a conditional, outside any lambda or
+/// let body: in order, the last in tail position, so that a `def` among
+/// them binds a global just as it would on its own.
+fn eval_sequence<'t>(
+    xs: &'t [ParseTree],
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    match xs.split_last() {
+        Some((last, init)) => {
+            for expr in init {
+                eval(expr, env, global_env)?;
+            }
+            Ok(Step::Eval(last, env.clone()))
+        }
+        None => Ok(Step::Done(StutterObject::Nil)),
+    }
+}
+
+/// `and` stops at the first false value and `or` at the first true one,
+/// returning it. Otherwise the last expression is the result, evaluated in
+/// tail position.
+fn eval_and_or<'t>(
+    op: &Op,
+    xs: &'t [ParseTree],
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    let stop_when = op == &Op::Or;
+    match xs.split_last() {
+        Some((last, init)) => {
+            for expr in init.iter() {
+                let value = eval(expr, env, global_env)?;
+                if is_true(&value)? == stop_when {
+                    return Ok(Step::Done(value));
+                }
+            }
+            Ok(Step::Eval(last, env.clone()))
+        }
+        None => Ok(Step::Done(StutterObject::Bool(!stop_when))),
+    }
+}
+
+fn eval_when<'t>(
+    op: &Op,
+    xs: &'t [ParseTree],
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    let form = format!("({} CONDITION EXPR...)", op_to_string(op));
+    match xs.split_first() {
+        Some((condition, body)) => {
+            let condition = eval(condition, env, global_env)?;
+            if is_true(&condition)? != (op == &Op::When) {
+                Ok(Step::Done(StutterObject::Nil))
+            } else if body.is_empty() {
+                Err(ErrorKind::Syntax(format!(
+                    "expecting form of {}, got an empty body",
+                    form
+                ))
+                .into())
+            } else {
+                eval_sequence(body, env, global_env)
+            }
+        }
+        None => {
+            Err(ErrorKind::Syntax(format!("expecting form of {}", form))
+                .into())
+        }
+    }
+}
+
+/// What a `cond` or `case` clause starts with: `else`, a name, or any other
+/// expression.
+enum ClauseHead<'t> {
+    Else,
+    Name(&'t String),
+    Tree(&'t ParseTree),
+}
+
+/// Splits a clause such as `((= x 1) EXPR...)` or `(else EXPR...)` into
+/// its head and body.
+fn split_clause<'t>(
+    form: &str,
+    clause: &'t ParseTree,
+) -> Result<(ClauseHead<'t>, &'t [ParseTree]), StutterError> {
+    match clause {
+        ParseTree::Branch(Op::Call, xs, _) if xs.len() >= 2 => {
+            Ok((ClauseHead::Tree(&xs[0]), &xs[1..]))
+        }
+        ParseTree::Branch(Op::Func(name), xs, _) if !xs.is_empty() => {
+            if name == "else" {
+                Ok((ClauseHead::Else, xs))
+            } else {
+                Ok((ClauseHead::Name(name), xs))
+            }
+        }
+        _ => Err(ErrorKind::Syntax(format!(
+            "expecting clauses of form {}",
+            form
+        ))
+        .into()),
+    }
+}
+
+/// Evaluates the body of the first clause whose test is true, or gives
+/// `Nil` if none is.
+fn eval_cond<'t>(
+    xs: &'t [ParseTree],
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    let form = "(TEST EXPR...)";
+    for clause in xs.iter() {
+        let (head, body) = split_clause(form, clause)?;
+        let matched = match head {
+            ClauseHead::Else => true,
+            ClauseHead::Name(name) => {
+                is_true(&lookup_env_string(name, env, global_env)?)?
+            }
+            ClauseHead::Tree(test) => is_true(&eval(test, env, global_env)?)?,
+        };
+        if matched {
+            return eval_sequence(body, env, global_env);
+        }
+    }
+    Ok(Step::Done(StutterObject::Nil))
+}
+
+/// Evaluates the key once and compares it against each clause's literal,
+/// or list of literals, without evaluating them.
+fn eval_case<'t>(
+    xs: &'t [ParseTree],
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    let form = "(DATUM EXPR...)";
+    let (key, clauses) = match xs.split_first() {
+        Some((key, clauses)) => (eval(key, env, global_env)?, clauses),
+        None => {
+            return Err(ErrorKind::Syntax(String::from(
+                "expecting form of (case KEY (DATUM EXPR...)...)",
+            ))
+            .into())
+        }
+    };
+    for clause in clauses.iter() {
+        let (head, body) = split_clause(form, clause)?;
+        let matched = match head {
+            ClauseHead::Else => true,
+            ClauseHead::Name(name) => {
+                key == StutterObject::Symbol(name.to_string())
+            }
+            ClauseHead::Tree(datum) => match &tree_to_data(datum)? {
+                StutterObject::List(choices) => choices.contains(&key),
+                datum => *datum == key,
+            },
+        };
+        if matched {
+            return eval_sequence(body, env, global_env);
+        }
+    }
+    Ok(Step::Done(StutterObject::Nil))
+}
+
+fn unpack_string_from_leaf(tree: &ParseTree) -> Result<String, StutterError> {
+    match tree {
+        ParseTree::Leaf(id, _) => match id {
+            Token::Id(s) => Ok(s.to_string()),
+            _ => {
+                Err(ErrorKind::Syntax(String::from("expecting function name"))
+                    .into())
+            }
+        },
+        ParseTree::Branch(_op, _v, _) => Err(ErrorKind::Syntax(String::from(
+            "expecting function name, got branch",
+        ))
+        .into()),
+    }
+}
+
+/// Evaluates `(def VAR EXPR...)`, or `(def (NAME PARAMS...) EXPR...)` as
+/// shorthand for defining a lambda, into the name and value to bind.
+fn eval_def(
+    xs: &[ParseTree],
+    span: &Span,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<(String, StutterObject), StutterError> {
+    if xs.len() < 2 {
+        return Err(ErrorKind::Syntax(String::from(
+            "expecting form of (def VAR EXPR...)",
+        ))
+        .into());
+    }
+    let body = body_tree(&xs[1..], span);
+    let (name, value) = match &xs[0] {
+        ParseTree::Branch(Op::Func(name), terms, _) => {
+            let closure = Closure {
+                params: Rc::new(parse_param_terms(None, terms)?),
+                body: Rc::new(body),
+                env: env.clone(),
+                name: Some(name.to_string()),
+                group: None,
+            };
+            (name.to_string(), StutterObject::Lambda(Rc::new(closure)))
+        }
+        var => {
+            let name = unpack_string_from_leaf(var)?;
+            let value = eval(&body, env, global_env)?;
+            let value = match &xs[1..] {
+                [expr] => name_lambda(&name, expr, value),
+                _ => value,
+            };
+            (name.clone(), value)
+        }
+    };
+    Ok((name, value))
+}
+
+/// The code a lambda or macro runs. Unless that is one expression that
+/// can't define anything, it is read as `(let () EXPR...)`, so that any
+/// `def` in it, even one a macro or `begin` gives, stays local.
+fn body_tree(body: &[ParseTree], span: &Span) -> ParseTree {
+    match body {
+        [expr]
+            if !matches!(
+                expr,
+                ParseTree::Branch(
+                    Op::Def | Op::Begin | Op::Func(_) | Op::Call,
+                    _,
+                    _
+                )
+            ) =>
+        {
+            expr.clone()
+        }
+        _ => {
+            let mut xs =
+                vec![ParseTree::Branch(Op::List, vec![], span.clone())];
+            xs.extend_from_slice(body);
+            ParseTree::Branch(Op::Let, xs, span.clone())
+        }
+    }
+}
+
+fn resolve_exprs(
+    xs: &[ParseTree],
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Vec<StutterObject>, StutterError> {
+    xs.iter().map(|expr| eval(expr, env, global_env)).collect()
+}
+
+pub(crate) fn expect_args(
+    form: &str,
+    v: &[StutterObject],
+    n: usize,
+) -> Result<(), StutterError> {
+    if v.len() != n {
+        Err(ErrorKind::Arity(format!(
+            "expecting form of {}, got {} arguments",
+            form,
+            v.len()
+        ))
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Joins `v` with spaces the way `print` and `error` show it: strings
+/// appear as their contents rather than quoted.
+fn join_for_display(v: &[StutterObject]) -> String {
+    let parts: Vec<String> = v
+        .iter()
+        .map(|obj| match obj {
+            StutterObject::Str(s) => s.clone(),
+            _ => obj.to_string(),
+        })
+        .collect();
+    parts.join(" ")
+}
+
+fn out_of_range(form: &str, i: usize, len: usize) -> StutterError {
+    ErrorKind::IndexOutOfRange(format!(
+        "{}: {} out of range for list of length {}",
+        form, i, len
+    ))
+    .into()
+}
+
+fn expect_str<'a>(
+    form: &str,
+    obj: &'a StutterObject,
+) -> Result<&'a String, StutterError> {
+    match obj {
+        StutterObject::Str(s) => Ok(s),
+        _ => Err(ErrorKind::Type(format!(
+            "type error: expected form {}, got {}",
+            form, obj
+        ))
+        .into()),
+    }
+}
+
+fn eval_string_op(
+    op: &Op,
+    v: &[StutterObject],
+) -> Result<StutterObject, StutterError> {
+    match op {
+        Op::StrLen => {
+            let form = "(str-len STRING)";
+            expect_args(form, v, 1)?;
+            let s = expect_str(form, &v[0])?;
+            Ok(StutterObject::Int(usize_to_bigint(s.chars().count())?))
+        }
+        Op::Substr => {
+            let form = "(substr START END STRING)";
+            expect_args(form, v, 3)?;
+            let s = expect_str(form, &v[2])?;
+            match (&v[0], &v[1]) {
+                (StutterObject::Int(start), StutterObject::Int(end)) => {
+                    let start = bigint_to_usize(start)?;
+                    let end = bigint_to_usize(end)?;
+                    let len = s.chars().count();
+                    if start > end || end > len {
+                        return Err(ErrorKind::IndexOutOfRange(format!(
+                            "substr: range {}..{} out of bounds for string \
+                             of length {}",
+                            start, end, len
+                        ))
+                        .into());
+                    }
+                    let sub = s.chars().skip(start).take(end - start);
+                    Ok(StutterObject::Str(sub.collect()))
+                }
+                _ => Err(ErrorKind::Type(format!(
+                    "type error: expected form {}",
+                    form
+                ))
+                .into()),
+            }
+        }
+        Op::StrCat => {
+            let mut cat = String::new();
+            for item in v.iter() {
+                cat.push_str(expect_str("(str-cat STRING...)", item)?);
+            }
+            Ok(StutterObject::Str(cat))
+        }
+        Op::Split => {
+            let form = "(split SEPARATOR STRING)";
+            expect_args(form, v, 2)?;
+            let sep = expect_str(form, &v[0])?;
+            let s = expect_str(form, &v[1])?;
+            let pieces: Vector<StutterObject> = if sep.is_empty() {
+                s.chars()
+                    .map(|c| StutterObject::Str(c.to_string()))
+                    .collect()
+            } else {
+                s.split(sep.as_str())
+                    .map(|piece| StutterObject::Str(piece.to_string()))
+                    .collect()
+            };
+            Ok(StutterObject::List(pieces))
+        }
+        Op::Join => {
+            let form = "(join SEPARATOR LIST)";
+            expect_args(form, v, 2)?;
+            let sep = expect_str(form, &v[0])?;
+            match as_list(&v[1]) {
+                Some(l) => {
+                    let strings: Result<Vec<&str>, StutterError> = l
+                        .iter()
+                        .map(|item| expect_str(form, item).map(|s| s.as_str()))
+                        .collect();
+                    Ok(StutterObject::Str(strings?.join(sep)))
+                }
+                _ => Err(ErrorKind::Type(format!(
+                    "type error: expected form {}",
+                    form
+                ))
+                .into()),
+            }
+        }
+        Op::Upper => {
+            let form = "(upper STRING)";
+            expect_args(form, v, 1)?;
+            Ok(StutterObject::Str(expect_str(form, &v[0])?.to_uppercase()))
+        }
+        Op::Lower => {
+            let form = "(lower STRING)";
+            expect_args(form, v, 1)?;
+            Ok(StutterObject::Str(expect_str(form, &v[0])?.to_lowercase()))
+        }
+        Op::Trim => {
+            let form = "(trim STRING)";
+            expect_args(form, v, 1)?;
+            let s = expect_str(form, &v[0])?;
+            Ok(StutterObject::Str(s.trim().to_string()))
+        }
+        Op::Contains => {
+            let form = "(contains SUBSTRING STRING)";
+            expect_args(form, v, 2)?;
+            let sub = expect_str(form, &v[0])?;
+            let s = expect_str(form, &v[1])?;
+            Ok(StutterObject::Bool(s.contains(sub.as_str())))
+        }
+        Op::StartsWith => {
+            let form = "(starts-with PREFIX STRING)";
+            expect_args(form, v, 2)?;
+            let prefix = expect_str(form, &v[0])?;
+            let s = expect_str(form, &v[1])?;
+            Ok(StutterObject::Bool(s.starts_with(prefix.as_str())))
+        }
+        Op::ToStr => {
+            expect_args("(to-string EXPR)", v, 1)?;
+            match &v[0] {
+                StutterObject::Str(s) => Ok(StutterObject::Str(s.clone())),
+                obj => Ok(StutterObject::Str(obj.to_string())),
+            }
+        }
+        Op::ParseInt => {
+            let form = "(parse-int STRING)";
+            expect_args(form, v, 1)?;
+            let s = expect_str(form, &v[0])?;
+            match BigInt::parse_bytes(s.trim().as_bytes(), 10) {
+                Some(i) => Ok(StutterObject::Int(i)),
+                None => Err(ErrorKind::Value(format!(
+                    "parse-int: invalid integer: {:?}",
+                    s
+                ))
+                .into()),
+            }
+        }
+        _ => Err(ErrorKind::Type(format!(
+            "{:?} is not a string operation",
+            op
+        ))
+        .into()),
+    }
+}
+
+fn eval_branch<'t>(
+    op: &Op,
+    xs: &'t [ParseTree],
+    span: &Span,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    match op {
+        Op::Func(name) => eval_func(name, xs, span, env, global_env),
+        Op::Call => eval_call(xs, span, env, global_env),
+        Op::Let | Op::Letrec => eval_let(op, xs, env, global_env),
+        Op::If => eval_if(xs, env, global_env),
+        Op::And | Op::Or => eval_and_or(op, xs, env, global_env),
+        Op::When | Op::Unless => eval_when(op, xs, env, global_env),
+        Op::Cond => eval_cond(xs, env, global_env),
+        Op::Case => eval_case(xs, env, global_env),
+        Op::Begin => eval_sequence(xs, env, global_env),
+        Op::Def
+        | Op::Quote
+        | Op::Defmacro
+        | Op::Quasiquote
+        | Op::Unquote
+        | Op::UnquoteSplicing => Ok(Step::Done(eval_special_form(
+            op, xs, span, env, global_env,
+        )?)),
+        _ => {
+            let args = resolve_exprs(xs, env, global_env)?;
+            Ok(Step::Done(call_builtin(op, &args, span, global_env)?))
+        }
+    }
+}
+
+fn eval_special_form(
+    op: &Op,
+    xs: &[ParseTree],
+    span: &Span,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    match op {
+        Op::Def => {
+            let (name, value) = eval_def(xs, span, env, global_env)?;
+            global_env.insert(name, value);
+            Ok(StutterObject::Nil)
+        }
+
+        Op::Quote => {
+            if xs.len() != 1 {
+                Err(ErrorKind::Syntax(String::from("expecting: (quote ITEM)"))
+                    .into())
+            } else {
+                tree_to_data(&xs[0])
+            }
+        }
+
+        Op::Defmacro => {
+            if xs.len() < 3 {
+                return Err(ErrorKind::Syntax(String::from(
+                    "expecting form of (defmacro NAME (PARAMS...) EXPR...)",
+                ))
+                .into());
+            }
+            let name = unpack_string_from_leaf(&xs[0])?;
+            let params = parse_params(&xs[1])?;
+            let closure = Closure {
+                params: Rc::new(params),
+                body: Rc::new(body_tree(&xs[2..], span)),
+                env: env.clone(),
+                name: Some(name.clone()),
+                group: None,
+            };
+            global_env.insert(name, StutterObject::Macro(Rc::new(closure)));
+            Ok(StutterObject::Nil)
+        }
+
+        Op::Quasiquote => {
+            if xs.len() != 1 {
+                Err(ErrorKind::Syntax(String::from(
+                    "expecting: (quasiquote ITEM)",
+                ))
+                .into())
+            } else {
+                quasiquote(&xs[0], 1, env, global_env)
+            }
+        }
+
+        Op::Unquote | Op::UnquoteSplicing => Err(ErrorKind::Syntax(format!(
+            "{} outside of quasiquote",
+            op_to_string(op)
+        ))
+        .into()),
+
+        _ => Err(ErrorKind::Syntax(format!("{:?} is not a special form", op))
+            .into()),
+    }
+}
+
+/// Applies a builtin to evaluated arguments. Most builtins only need their
+/// arguments; the macro expanders and `eval` also need to see the global
+/// environment, and `eval` attributes the code it runs to the call's `span`.
+fn call_builtin(
+    op: &Op,
+    args: &[StutterObject],
+    span: &Span,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    match op {
+        Op::Eval => {
+            expect_args("(eval FORM)", args, 1)?;
+            let tree = data_to_tree(&args[0], span)?;
+            eval(&tree, &HashTrieMap::new(), global_env)
+        }
+        Op::MacroExpand | Op::MacroExpand1 => {
+            let form = format!("({} FORM)", op_to_string(op));
+            expect_args(&form, args, 1)?;
+            macroexpand(&args[0], op == &Op::MacroExpand, global_env)
+        }
+        Op::Update => {
+            let form = "(update MAP KEY FUNCTION ARG...)";
+            if args.len() < 3 {
+                return Err(ErrorKind::Arity(format!(
+                    "expecting form of {}, got {} arguments",
+                    form,
+                    args.len()
+                ))
+                .into());
+            }
+            let map = expect_map(form, &args[0])?;
+            let key = MapKey::from_object(&args[1])?;
+            let old = map.get(&key).cloned().unwrap_or(StutterObject::Nil);
+            let mut call_args = vec![old];
+            call_args.extend_from_slice(&args[3..]);
+            let new =
+                call_function(args[2].clone(), call_args, span, global_env)?;
+            Ok(StutterObject::Map(map.insert(key, new)))
+        }
+        _ => apply_builtin(op, args),
+    }
+}
+
+fn apply_builtin(
+    op: &Op,
+    v: &[StutterObject],
+) -> Result<StutterObject, StutterError> {
+    match op {
+        Op::Add
+        | Op::Pow
+        | Op::Sub
+        | Op::Mul
+        | Op::Div
+        | Op::IntDiv
+        | Op::Mod
+        | Op::Eq
+        | Op::Gt
+        | Op::Lt
+        | Op::Gte
+        | Op::Lte => reduce(op, v),
+
+        Op::List => Ok(StutterObject::List(v.iter().cloned().collect())),
+        Op::Index => {
+            expect_args("(index NUM LIST)", v, 2)?;
+            let i = &v[0];
+            let list = &v[1];
+            match (i, as_list(list)) {
+                (StutterObject::Int(n), Some(l)) => {
+                    let size: usize = bigint_to_usize(n)?;
+                    match l.get(size) {
+                        Some(item) => Ok(item.clone()),
+                        None => Err(out_of_range("index", size, l.len())),
+                    }
+                }
+                _ => Err(ErrorKind::Type(String::from(
+                    "type error: expected form (index NUM LIST)",
+                ))
+                .into()),
+            }
+        }
+        Op::Take => {
+            expect_args("(take NUM LIST)", v, 2)?;
+            let i = &v[0];
+            let list = &v[1];
+            match (i, as_list(list)) {
+                (StutterObject::Int(n), Some(l)) => {
+                    let size: usize = bigint_to_usize(n)?;
+                    if size <= l.len() {
+                        Ok(StutterObject::List(l.take(size)))
+                    } else {
+                        Err(out_of_range("take", size, l.len()))
+                    }
+                }
+                _ => Err(ErrorKind::Type(String::from(
+                    "type error: expected form (take NUM LIST)",
+                ))
+                .into()),
+            }
+        }
+        Op::Drop => {
+            expect_args("(drop NUM LIST)", v, 2)?;
+            let i = &v[0];
+            let list = &v[1];
+            match (i, as_list(list)) {
+                (StutterObject::Int(n), Some(l)) => {
+                    if !n.is_positive() {
+                        Ok(StutterObject::List(Vector::new()))
+                    } else {
+                        let size: usize = bigint_to_usize(n)?;
+                        if size <= l.len() {
+                            Ok(StutterObject::List(l.skip(size)))
+                        } else {
+                            Err(out_of_range("drop", size, l.len()))
+                        }
+                    }
+                }
+                _ => Err(ErrorKind::Type(String::from(
+                    "type error: expected form (drop NUM LIST)",
+                ))
+                .into()),
+            }
+        }
+        Op::Append => {
+            expect_args("(append ITEM LIST)", v, 2)?;
+            let i = &v[0];
+            let list = &v[1];
+            match as_list(list) {
+                Some(mut vec) => {
+                    vec.push_back(i.clone());
+                    Ok(StutterObject::List(vec))
+                }
+                _ => Err(ErrorKind::Type(String::from(
+                    "type error: expected form (append ITEM LIST)",
+                ))
+                .into()),
+            }
+        }
+        Op::ToReal => {
+            expect_args("(real INT)", v, 1)?;
+            let num = &v[0];
+            match num {
+                StutterObject::Int(i) => {
+                    let r = bigint_to_f64(i)?;
+                    Ok(StutterObject::Real(r))
+                }
+                StutterObject::Ratio(r) => {
+                    Ok(StutterObject::Real(ratio_to_f64(r)?))
+                }
+                _ => Err(ErrorKind::Type(String::from(
+                    "type error: expected form (real INT)",
+                ))
+                .into()),
+            }
+        }
+        Op::ToInt => {
+            expect_args("(int REAL)", v, 1)?;
+            let num = &v[0];
+            match num {
+                StutterObject::Real(r) => {
+                    let i = f64_to_bigint(*r)?;
+                    Ok(StutterObject::Int(i))
+                }
+                StutterObject::Ratio(r) => {
+                    Ok(StutterObject::Int(r.trunc().to_integer()))
+                }
+                _ => Err(ErrorKind::Type(String::from(
+                    "type error: expected form (real INT)",
+                ))
+                .into()),
+            }
+        }
+        Op::Range => {
+            let (bounds, step) = match v {
+                [lower, upper] => ([lower, upper], None),
+                [lower, upper, StutterObject::Keyword(k), step]
+                    if k == "step" =>
+                {
+                    ([lower, upper], Some(step))
+                }
+                [_, _, StutterObject::Keyword(k), _] => {
+                    return Err(ErrorKind::Value(format!(
+                        "range got unknown keyword :{}",
+                        k
+                    ))
+                    .into())
+                }
+                _ => {
+                    return Err(ErrorKind::Arity(format!(
+                        "range expecting 2 arguments and an optional :step, \
+                         got {}",
+                        v.len()
+                    ))
+                    .into())
+                }
+            };
+            let step = match step {
+                None => 1,
+                Some(StutterObject::Int(step)) if !step.is_zero() => {
+                    bigint_to_i64(step)?
+                }
+                Some(step) => {
+                    return Err(ErrorKind::Value(format!(
+                        "range expecting a non-zero integer step, got {}",
+                        step
+                    ))
+                    .into())
+                }
+            };
+            match bounds {
+                [StutterObject::Int(bi1), StutterObject::Int(bi2)] => {
+                    let mut vector = Vector::new();
+                    let mut i = bigint_to_i64(bi1)?;
+                    let end = bigint_to_i64(bi2)?;
+                    while (step > 0 && i < end) || (step < 0 && i > end) {
+                        vector
+                            .push_back(StutterObject::Int(i64_to_bigint(i)?));
+                        i = match i.checked_add(step) {
+                            Some(next) => next,
+                            None => break,
+                        };
+                    }
+                    Ok(StutterObject::List(vector))
+                }
+                [lower_bound, upper_bound] => Err(ErrorKind::Type(format!(
+                    "unsupported types for range: {:?}, {:?}",
+                    lower_bound, upper_bound
+                ))
+                .into()),
+            }
+        }
+        Op::Cat => {
+            let mut master_vec = Vector::new();
+            for list in v.iter() {
+                match as_list(list) {
+                    Some(vec) => master_vec.append(vec),
+                    None => {
+                        return Err(ErrorKind::Type(format!(
+                            "cat: expecting list, got {:?}",
+                            list
+                        ))
+                        .into())
+                    }
+                }
+            }
+            Ok(StutterObject::List(master_vec))
+        }
+        Op::Len => {
+            expect_args("(len LIST)", v, 1)?;
+            let list = &v[0];
+            match list {
+                StutterObject::List(l) => {
+                    let len: BigInt = usize_to_bigint(l.len())?;
+                    Ok(StutterObject::Int(len))
+                }
+                StutterObject::Nil => Ok(StutterObject::Int(BigInt::zero())),
+                StutterObject::Map(m) => {
+                    Ok(StutterObject::Int(usize_to_bigint(m.size())?))
+                }
+                StutterObject::Set(s) => {
+                    Ok(StutterObject::Int(usize_to_bigint(s.size())?))
+                }
+                _ => Err(ErrorKind::Type(String::from(
+                    "type error: expected form (len LIST)",
+                ))
+                .into()),
+            }
+        }
+        Op::StrLen
+        | Op::Substr
+        | Op::StrCat
+        | Op::Split
+        | Op::Join
+        | Op::Upper
+        | Op::Lower
+        | Op::Trim
+        | Op::Contains
+        | Op::StartsWith
+        | Op::ToStr
+        | Op::ParseInt => eval_string_op(op, v),
+        Op::HashMap
+        | Op::Get
+        | Op::Assoc
+        | Op::Dissoc
+        | Op::Keys
+        | Op::Vals
+        | Op::HasKey
+        | Op::Merge => eval_map_op(op, v),
+        Op::HashSet
+        | Op::ToSet
+        | Op::Union
+        | Op::Intersection
+        | Op::Difference
+        | Op::IsSubset
+        | Op::IsMember => eval_set_op(op, v),
+        Op::Raise => Err(ErrorKind::UserRaised(join_for_display(v)).into()),
+        Op::Print => {
+            print_line(&join_for_display(v))?;
+            Ok(StutterObject::Nil)
+        }
+        Op::Gensym => {
+            expect_args("(gensym)", v, 0)?;
+            Ok(gensym())
+        }
+        Op::Not => {
+            expect_args("(not EXPR)", v, 1)?;
+            Ok(StutterObject::Bool(!is_true(&v[0])?))
+        }
+        Op::IsList => {
+            expect_args("(list? EXPR)", v, 1)?;
+            Ok(StutterObject::Bool(matches!(
+                v[0],
+                StutterObject::List(_) | StutterObject::Nil
+            )))
+        }
+        Op::IsPair => {
+            expect_args("(pair? EXPR)", v, 1)?;
+            Ok(StutterObject::Bool(
+                matches!(&v[0], StutterObject::List(l) if !l.is_empty()),
+            ))
+        }
+        Op::IsEmpty => {
+            let l = expect_list("(empty? LIST)", v)?;
+            Ok(StutterObject::Bool(l.is_empty()))
+        }
+        Op::Numerator | Op::Denominator => {
+            let form = format!("({} RATIO)", op_to_string(op));
+            expect_args(&form, v, 1)?;
+            let r = match &v[0] {
+                StutterObject::Int(n) => BigRational::from_integer(n.clone()),
+                StutterObject::Ratio(r) => r.clone(),
+                other => {
+                    return Err(ErrorKind::Type(format!(
+                        "type error: expected form {}, got {}",
+                        form, other
+                    ))
+                    .into())
+                }
+            };
+            Ok(StutterObject::Int(match op {
+                Op::Numerator => r.numer().clone(),
+                _ => r.denom().clone(),
+            }))
+        }
+        Op::Cons => {
+            expect_args("(cons ITEM LIST)", v, 2)?;
+            let mut l = expect_list("(cons ITEM LIST)", &v[1..])?;
+            l.push_front(v[0].clone());
+            Ok(StutterObject::List(l))
+        }
+        Op::First => {
+            let l = expect_list("(first LIST)", v)?;
+            Ok(l.head().cloned().unwrap_or(StutterObject::Nil))
+        }
+        Op::Rest => {
+            let mut l = expect_list("(rest LIST)", v)?;
+            l.pop_front();
+            Ok(StutterObject::List(l))
+        }
+        Op::Func(_)
+        | Op::Call
+        | Op::Let
+        | Op::If
+        | Op::Def
+        | Op::Quote
+        | Op::Defmacro
+        | Op::Quasiquote
+        | Op::Unquote
+        | Op::UnquoteSplicing
+        | Op::MacroExpand
+        | Op::MacroExpand1
+        | Op::Eval
+        | Op::And
+        | Op::Or
+        | Op::Cond
+        | Op::When
+        | Op::Unless
+        | Op::Case
+        | Op::Begin
+        | Op::Letrec
+        | Op::Update
+        | Op::Apply => Err(ErrorKind::Syntax(format!(
+            "{:?} is not a builtin function",
+            op
+        ))
+        .into()),
+    }
+}
+
+/// Parses a parameter list such as `(a b &optional (c 1) d &rest more)`.
+fn parse_params(params: &ParseTree) -> Result<Params, StutterError> {
+    match params {
+        ParseTree::Branch(Op::List, terms, _) if terms.is_empty() => {
+            Ok(Params::default())
+        }
+        ParseTree::Branch(Op::Func(first), terms, _) => {
+            parse_param_terms(Some(first), terms)
+        }
+        _ => {
+            Err(ErrorKind::Syntax(String::from("expecting first param"))
+                .into())
+        }
+    }
+}
+
+/// Parses the parameters `first` and then `terms`, which the parser has
+/// already split apart.
+fn parse_param_terms(
+    first: Option<&String>,
+    terms: &[ParseTree],
+) -> Result<Params, StutterError> {
+    let syntax_error = |msg: &str| -> StutterError {
+        ErrorKind::Syntax(msg.to_string()).into()
+    };
+    let mut items: Vec<(String, Option<ParseTree>)> =
+        first.iter().map(|name| (name.to_string(), None)).collect();
+    for term in terms.iter() {
+        match term {
+            ParseTree::Leaf(Token::Id(s), _) => {
+                items.push((s.to_string(), None))
+            }
+            ParseTree::Branch(Op::Func(name), default, _)
+                if default.len() == 1 =>
+            {
+                items.push((name.to_string(), Some(default[0].clone())))
+            }
+            _ => return Err(syntax_error("expecting param list")),
+        }
+    }
+
+    #[derive(PartialEq, PartialOrd)]
+    enum Section {
+        Required,
+        Optional,
+        Rest,
+        Key,
+    }
+    let mut parsed = Params::default();
+    let mut section = Section::Required;
+    let missing_rest = |section: &Section, parsed: &Params| {
+        *section == Section::Rest && parsed.rest.is_none()
+    };
+    for (name, default) in items {
+        let next = match name.as_str() {
+            "&optional" => Some(Section::Optional),
+            "&rest" => Some(Section::Rest),
+            "&key" => Some(Section::Key),
+            _ => None,
+        };
+        match (next, default) {
+            (Some(next), None) if next > section => {
+                if missing_rest(&section, &parsed) {
+                    return Err(syntax_error(
+                        "expecting one param after &rest",
+                    ));
+                }
+                section = next;
+            }
+            (Some(_), _) => {
+                return Err(ErrorKind::Syntax(format!(
+                    "unexpected {} in param list",
+                    name
+                ))
+                .into())
+            }
+            (None, None) if section == Section::Required => {
+                parsed.required.push(name)
+            }
+            (None, default) if section == Section::Optional => {
+                parsed.optional.push((name, default))
+            }
+            (None, None) if missing_rest(&section, &parsed) => {
+                parsed.rest = Some(name)
+            }
+            (None, _) if section == Section::Rest => {
+                return Err(syntax_error("expecting one param after &rest"))
+            }
+            (None, default) if section == Section::Key => {
+                parsed.keys.push((name, default))
+            }
+            (None, _) => {
+                return Err(syntax_error(
+                    "only &optional and &key params can have a default",
+                ))
+            }
+        }
+    }
+    if missing_rest(&section, &parsed) {
+        return Err(syntax_error("expecting one param after &rest"));
+    }
+    Ok(parsed)
+}
+
+fn eval_step<'t>(
+    tree: &'t ParseTree,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Step<'t>, StutterError> {
+    match tree {
+        ParseTree::Branch(op, xs, span) => match op {
+            Op::Func(s) => {
+                if s != "lambda" {
+                    eval_branch(op, xs, span, env, global_env)
+                } else {
+                    if xs.len() < 2 {
+                        return Err(ErrorKind::Syntax(String::from(
+                            "expecting form of (lambda (PARAMS...) EXPR...)",
+                        ))
+                        .into());
+                    }
+                    let params = &xs[0];
+                    Ok(Step::Done(StutterObject::Lambda(Rc::new(Closure {
+                        params: Rc::new(parse_params(params)?),
+                        body: Rc::new(body_tree(&xs[1..], span)),
+                        env: env.clone(),
+                        name: None,
+                        group: None,
+                    }))))
+                }
+            }
+            _ => eval_branch(op, xs, span, env, global_env),
+        },
+        ParseTree::Leaf(tok, _) => {
+            let obj = token_to_stutterobject(tok)?;
+            match obj {
+                StutterObject::Id(_) => {
+                    Ok(Step::Done(lookup_env(&obj, env, global_env)?))
+                }
+                _ => Ok(Step::Done(obj)),
+            }
+        }
+    }
+}
+
+/// How deeply `eval` may nest before giving up. Tail calls don't count
+/// towards this, only evaluation that has to come back to its caller.
+pub(crate) const MAX_EVAL_DEPTH: usize = 10_000;
+
+thread_local! {
+    static EVAL_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static TRUTHINESS: Cell<Truthiness> =
+        const { Cell::new(Truthiness::Strict) };
+    static OUTPUT: RefCell<Option<Output>> = const { RefCell::new(None) };
+}
+
+/// Where `print` writes, shared between an interpreter and its runs.
+pub(crate) type Output = Rc<RefCell<Box<dyn Write>>>;
+
+/// Writes `line` to the output of the running interpreter, or to stdout
+/// outside of one.
+fn print_line(line: &str) -> Result<(), StutterError> {
+    OUTPUT
+        .with(|output| match &*output.borrow() {
+            Some(output) => writeln!(output.borrow_mut(), "{}", line),
+            None => writeln!(io::stdout(), "{}", line),
+        })
+        .map_err(|e| ErrorKind::Io(format!("could not print: {}", e)).into())
+}
+
+/// Counts one level of `eval` nesting for as long as it is alive.
+struct DepthGuard;
+
+impl DepthGuard {
+    fn enter() -> Result<DepthGuard, StutterError> {
+        EVAL_DEPTH.with(|depth| {
+            if depth.get() >= MAX_EVAL_DEPTH {
+                Err(ErrorKind::RecursionLimit.into())
+            } else {
+                depth.set(depth.get() + 1);
+                Ok(DepthGuard)
+            }
+        })
+    }
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        EVAL_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// How much stack must be left before `with_stack` moves to a new segment.
+/// This has to cover the deepest a recursion goes between two checks.
+const STACK_RED_ZONE: usize = 256 * 1024;
+
+/// The size of each stack segment `with_stack` allocates.
+const STACK_SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+/// Runs `f`, first switching to a fresh stack segment on the heap when the
+/// current one is nearly used up. Everything that recurses on nested code
+/// or data goes through here, so hosts can call the interpreter from a
+/// thread with an ordinary stack.
+pub(crate) fn with_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, f)
+}
+
+pub(crate) fn eval(
+    tree: &ParseTree,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    with_stack(|| eval_nested(tree, env, global_env))
+}
+
+fn eval_nested(
+    tree: &ParseTree,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    let _depth = DepthGuard::enter().map_err(|e| e.at(tree.span()))?;
+    let mut tree = tree;
+    let mut env = env.clone();
+    let mut closure: Rc<Closure>;
+    let mut expansion: Rc<ParseTree>;
+    let mut frame: Option<String> = None;
+    loop {
+        let step = eval_step(tree, &env, global_env).map_err(|e| {
+            let e = e.at(tree.span());
+            match &frame {
+                Some(name) => e.in_frame(name),
+                None => e,
+            }
+        })?;
+        match step {
+            Step::Done(value) => return Ok(value),
+            Step::Eval(next_tree, next_env) => {
+                tree = next_tree;
+                env = next_env;
+            }
+            Step::Call(next_closure, next_env) => {
+                closure = next_closure;
+                frame = Some(
+                    closure
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| String::from("<lambda>")),
+                );
+                tree = &closure.body;
+                env = next_env;
+            }
+            Step::Expand(next_tree, next_env) => {
+                expansion = next_tree;
+                tree = &expansion;
+                env = next_env;
+            }
+        }
+    }
+}
+
+/// Puts a truthiness policy in force until it is dropped, then restores
+/// whichever was in force before, so that a native function can run
+/// another interpreter without changing the caller's policy.
+struct TruthinessGuard(Truthiness);
+
+impl TruthinessGuard {
+    fn enter(truthiness: Truthiness) -> TruthinessGuard {
+        TruthinessGuard(TRUTHINESS.with(|cell| cell.replace(truthiness)))
+    }
+}
+
+impl Drop for TruthinessGuard {
+    fn drop(&mut self) {
+        TRUTHINESS.with(|cell| cell.set(self.0));
+    }
+}
+
+/// Sends `print` to an output until it is dropped, then back to wherever
+/// it went before, in the same way as `TruthinessGuard`.
+struct OutputGuard(Option<Output>);
+
+impl OutputGuard {
+    fn enter(output: &Output) -> OutputGuard {
+        OutputGuard(OUTPUT.with(|cell| cell.replace(Some(output.clone()))))
+    }
+}
+
+impl Drop for OutputGuard {
+    fn drop(&mut self) {
+        OUTPUT.with(|cell| *cell.borrow_mut() = self.0.take());
+    }
+}
+
+/// Builtins that are bound in the global environment like any other value
+/// instead of being reserved by the lexer, so that programs can still use
+/// their names for variables and hosts can define over them.
+const GLOBAL_BUILTINS: &[Op] = &[
+    Op::StrLen,
+    Op::Substr,
+    Op::StrCat,
+    Op::Split,
+    Op::Join,
+    Op::Upper,
+    Op::Lower,
+    Op::Trim,
+    Op::Contains,
+    Op::StartsWith,
+    Op::ToStr,
+    Op::ParseInt,
+    Op::Raise,
+    Op::Print,
+    Op::MacroExpand,
+    Op::MacroExpand1,
+    Op::Gensym,
+    Op::Eval,
+    Op::Not,
+    Op::Apply,
+    Op::IsList,
+    Op::HashMap,
+    Op::Get,
+    Op::Assoc,
+    Op::Dissoc,
+    Op::Keys,
+    Op::Vals,
+    Op::HasKey,
+    Op::Merge,
+    Op::Update,
+    Op::HashSet,
+    Op::ToSet,
+    Op::Union,
+    Op::Intersection,
+    Op::Difference,
+    Op::IsSubset,
+    Op::IsMember,
+    Op::Cons,
+    Op::First,
+    Op::Rest,
+    Op::IsEmpty,
+    Op::IsPair,
+    Op::Numerator,
+    Op::Denominator,
+];
+
+pub(crate) fn define_builtins(
+    global_env: &mut HashMap<String, StutterObject>,
+) {
+    for op in GLOBAL_BUILTINS.iter() {
+        global_env.insert(
+            op_to_string(op).to_string(),
+            StutterObject::Builtin(op.clone()),
+        );
+    }
+}
+
+pub(crate) fn run(
+    source: &Rc<Source>,
+    global_env: &mut HashMap<String, StutterObject>,
+    truthiness: Truthiness,
+    output: &Output,
+) -> Result<StutterObject, StutterError> {
+    let _truthiness = TruthinessGuard::enter(truthiness);
+    let _output = OutputGuard::enter(output);
+    let tokens = lex(source)?;
+    let trees = parse(&tokens)?;
+    let env = HashTrieMap::new();
+    let mut result = StutterObject::Nil;
+    for tree in trees.iter() {
+        result = eval(tree, &env, global_env)?;
+    }
+    Ok(result)
+}
@@ -0,0 +1,226 @@
+// This file is part of Stutter.
+//
+// Stutter is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Stutter is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Stutter.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The embedding API: an `Interpreter` owns a global environment and
+//! evaluates Stutter source against it.
+
+use crate::convert::{FromStutter, IntoStutter};
+use crate::error::{ErrorKind, Source, StutterError};
+use crate::eval::{define_builtins, run, Output};
+use crate::lexer::{to_token, Token};
+use crate::object::{Arity, NativeFunction, StutterObject};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// The standard library compiled into the binary, loaded by
+/// `Interpreter::new`.
+pub const STDLIB: &str = include_str!("../stdlib.lisp");
+
+/// Which values `if`, `cond`, `and`, `or`, `when`, `unless`, `not` and
+/// `filter` predicates accept as true or false.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Truthiness {
+    /// Only `True` and `False` are conditions; anything else is an
+    /// `ErrorKind::Type` error.
+    #[default]
+    Strict,
+    /// `False` and `Nil` are false and every other value is true.
+    Lisp,
+}
+
+impl FromStr for Truthiness {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Truthiness, String> {
+        match s {
+            "strict" => Ok(Truthiness::Strict),
+            "lisp" => Ok(Truthiness::Lisp),
+            _ => Err(format!(
+                "unknown truthiness '{}', expecting strict or lisp",
+                s
+            )),
+        }
+    }
+}
+
+/// A Stutter interpreter and the global definitions made so far. Each
+/// call to `eval_str` or `load_file` sees everything `def`ed by the
+/// ones before it.
+///
+/// Evaluation that isn't in tail position may nest up to 10,000 levels
+/// before it is stopped with `ErrorKind::RecursionLimit`, and code may nest
+/// up to 1,000 levels before it is rejected. Deep evaluation grows the
+/// stack on the heap as it goes, so any thread can host an interpreter.
+pub struct Interpreter {
+    global_env: HashMap<String, StutterObject>,
+    truthiness: Truthiness,
+    output: Output,
+}
+
+impl Interpreter {
+    /// An interpreter with the built-in standard library loaded.
+    pub fn new() -> Interpreter {
+        let mut interpreter = Interpreter::empty();
+        interpreter
+            .eval_source("<stdlib>", STDLIB)
+            .expect("built-in stdlib failed to load");
+        interpreter
+    }
+
+    /// An interpreter with nothing but the builtins defined.
+    pub fn empty() -> Interpreter {
+        let mut global_env = HashMap::new();
+        define_builtins(&mut global_env);
+        Interpreter {
+            global_env,
+            truthiness: Truthiness::default(),
+            output: Rc::new(RefCell::new(Box::new(io::stdout()))),
+        }
+    }
+
+    /// Evaluates every form in `text` and returns the value of the last.
+    pub fn eval_str(
+        &mut self,
+        text: &str,
+    ) -> Result<StutterObject, StutterError> {
+        self.eval_source("<string>", text)
+    }
+
+    /// Like `eval_str`, but errors are reported against `name` rather
+    /// than `<string>`.
+    pub fn eval_source(
+        &mut self,
+        name: &str,
+        text: &str,
+    ) -> Result<StutterObject, StutterError> {
+        let source = Rc::new(Source {
+            name: name.to_string(),
+            text: text.to_string(),
+        });
+        run(&source, &mut self.global_env, self.truthiness, &self.output)
+    }
+
+    /// The truthiness policy conditions are evaluated under.
+    pub fn truthiness(&self) -> Truthiness {
+        self.truthiness
+    }
+
+    /// Changes the truthiness policy for everything evaluated from now
+    /// on, including functions defined earlier. The stdlib works under
+    /// either policy.
+    pub fn set_truthiness(&mut self, truthiness: Truthiness) {
+        self.truthiness = truthiness;
+    }
+
+    /// Sends what `print` writes to `output` instead of stdout, for
+    /// everything evaluated from now on. Failing to write is an
+    /// `ErrorKind::Io` error.
+    pub fn set_output<W: Write + 'static>(&mut self, output: W) {
+        self.output = Rc::new(RefCell::new(Box::new(output)));
+    }
+
+    /// Evaluates the file at `path`. A file that can't be read is an
+    /// `ErrorKind::Io` error.
+    pub fn load_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<StutterObject, StutterError> {
+        let path = path.as_ref();
+        let name = path.to_string_lossy().into_owned();
+        match fs::read_to_string(path) {
+            Ok(text) => self.eval_source(&name, &text),
+            Err(e) => {
+                Err(ErrorKind::Io(format!("could not read {}: {}", name, e))
+                    .into())
+            }
+        }
+    }
+
+    /// Binds `name` globally, as `(def name value)` would. Builtins that
+    /// are part of the syntax, such as `if` or `+`, can't be redefined:
+    /// their names are an `ErrorKind::Syntax` error.
+    pub fn define<T: IntoStutter>(
+        &mut self,
+        name: &str,
+        value: T,
+    ) -> Result<(), StutterError> {
+        if !matches!(to_token(&name.to_string()), Token::Id(_)) {
+            return Err(ErrorKind::Syntax(format!(
+                "'{}' is reserved and can't be defined",
+                name
+            ))
+            .into());
+        }
+        self.global_env
+            .insert(name.to_string(), value.into_stutter());
+        Ok(())
+    }
+
+    /// Makes a Rust function callable from Stutter as `name`. Calls with a
+    /// number of arguments `arity` doesn't allow are an `ErrorKind::Arity`
+    /// error and never reach `func`.
+    ///
+    /// ```
+    /// use stutter::{FromStutter, Interpreter, IntoStutter};
+    ///
+    /// let mut interpreter = Interpreter::new();
+    /// interpreter
+    ///     .register_fn("hypot", 2, |args| {
+    ///         let x = f64::from_stutter(&args[0])?;
+    ///         let y = f64::from_stutter(&args[1])?;
+    ///         Ok(x.hypot(y).into_stutter())
+    ///     })
+    ///     .unwrap();
+    /// let value = interpreter.eval_str("(hypot 3 4)").unwrap();
+    /// assert_eq!(f64::from_stutter(&value).unwrap(), 5.0);
+    /// ```
+    pub fn register_fn<A, F>(
+        &mut self,
+        name: &str,
+        arity: A,
+        func: F,
+    ) -> Result<(), StutterError>
+    where
+        A: Into<Arity>,
+        F: Fn(&[StutterObject]) -> Result<StutterObject, StutterError>
+            + 'static,
+    {
+        let native = NativeFunction {
+            name: name.to_string(),
+            arity: arity.into(),
+            func: Box::new(func),
+        };
+        self.define(name, StutterObject::Native(Rc::new(native)))
+    }
+
+    /// Looks up a global and converts it to `T`.
+    pub fn get<T: FromStutter>(&self, name: &str) -> Result<T, StutterError> {
+        match self.global_env.get(name) {
+            Some(value) => T::from_stutter(value),
+            None => Err(ErrorKind::Unbound(name.to_string()).into()),
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
@@ -0,0 +1,306 @@
+// This file is part of Stutter.
+//
+// Stutter is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Stutter is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Stutter.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Turns source text into tokens, each tagged with where it started.
+
+use crate::error::{ErrorKind, Source, Span, StutterError};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::Zero;
+use std::rc::Rc;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Token {
+    Lparen,             // (
+    Rparen,             // )
+    Plus,               // +
+    Minus,              // -
+    Times,              // *
+    Slash,              // /
+    DoubleSlash,        // //
+    Percent,            // %
+    Pow,                // pow
+    Gt,                 // >
+    Lt,                 // <
+    Eq,                 // =
+    Gte,                // >=
+    Lte,                // <=
+    Let,                // let
+    Def,                // def
+    List,               // list
+    Index,              // index
+    Drop,               // drop
+    Quote,              // quote
+    Append,             // append
+    Range,              // range
+    Cat,                // cat
+    Len,                // len
+    Take,               // take
+    If,                 // if
+    ToReal,             // real
+    ToInt,              // int
+    Defmacro,           // defmacro
+    Quasiquote,         // quasiquote
+    Unquote,            // unquote
+    UnquoteSplicing,    // unquote-splicing
+    Apostrophe,         // ' reader prefix
+    And,                // and
+    Or,                 // or
+    Cond,               // cond
+    When,               // when
+    Unless,             // unless
+    Case,               // case
+    Begin,              // begin or do
+    Letrec,             // letrec
+    Lbrace,             // {
+    Rbrace,             // }
+    HashLbrace,         // #{
+    Backquote,          // ` reader prefix
+    Comma,              // , reader prefix
+    CommaAt,            // ,@ reader prefix
+    Int(BigInt),        // Integer literal
+    Real(f64),          // Floating point literal
+    Ratio(BigRational), // Ratio literal such as 2/3
+    Bool(bool),         // Boolean literal
+    Nil,                // nil literal
+    Str(String),        // String literal
+    Keyword(String),    // :keyword literal
+    Id(String),         // identifier (variable name or function name)
+}
+
+/// Reads `NUM/DEN` as an exact fraction in lowest terms.
+fn parse_ratio(s: &str) -> Option<BigRational> {
+    let (numer, denom) = s.split_once('/')?;
+    if !denom.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let numer = BigInt::parse_bytes(numer.as_bytes(), 10)?;
+    let denom = BigInt::parse_bytes(denom.as_bytes(), 10)?;
+    if denom.is_zero() {
+        None
+    } else {
+        Some(BigRational::new(numer, denom))
+    }
+}
+
+pub(crate) fn to_token(s: &String) -> Token {
+    let bytes = s.as_bytes();
+    if let Some(t) = BigInt::parse_bytes(bytes, 10) {
+        Token::Int(t)
+    } else if let Some(t) = parse_ratio(s) {
+        if t.is_integer() {
+            Token::Int(t.to_integer())
+        } else {
+            Token::Ratio(t)
+        }
+    } else if let Ok(t) = s.parse::<f64>() {
+        Token::Real(t)
+    } else if let Ok(t) = s.parse::<bool>() {
+        Token::Bool(t)
+    } else if s.len() > 1 && s.starts_with(':') {
+        Token::Keyword(s[1..].to_string())
+    } else {
+        match s.as_ref() {
+            "(" => Token::Lparen,
+            ")" => Token::Rparen,
+            "+" => Token::Plus,
+            "-" => Token::Minus,
+            "*" => Token::Times,
+            "/" => Token::Slash,
+            "//" => Token::DoubleSlash,
+            "%" => Token::Percent,
+            "pow" => Token::Pow,
+            "<" => Token::Lt,
+            ">" => Token::Gt,
+            "=" => Token::Eq,
+            ">=" => Token::Gte,
+            "<=" => Token::Lte,
+            "let" => Token::Let,
+            "def" => Token::Def,
+            "list" => Token::List,
+            "take" => Token::Take,
+            "if" => Token::If,
+            "index" => Token::Index,
+            "drop" => Token::Drop,
+            "quote" => Token::Quote,
+            "append" => Token::Append,
+            "range" => Token::Range,
+            "cat" => Token::Cat,
+            "len" => Token::Len,
+            "real" => Token::ToReal,
+            "int" => Token::ToInt,
+            "defmacro" => Token::Defmacro,
+            "quasiquote" => Token::Quasiquote,
+            "unquote" => Token::Unquote,
+            "unquote-splicing" => Token::UnquoteSplicing,
+            "'" => Token::Apostrophe,
+            "and" => Token::And,
+            "or" => Token::Or,
+            "cond" => Token::Cond,
+            "when" => Token::When,
+            "unless" => Token::Unless,
+            "case" => Token::Case,
+            "begin" | "do" => Token::Begin,
+            "letrec" => Token::Letrec,
+            "{" => Token::Lbrace,
+            "}" => Token::Rbrace,
+            "#{" => Token::HashLbrace,
+            "nil" => Token::Nil,
+            "`" => Token::Backquote,
+            "," => Token::Comma,
+            ",@" => Token::CommaAt,
+            _ => Token::Id(s.to_string()),
+        }
+    }
+}
+
+/// Characters of a source text along with the line and column each one
+/// starts at.
+struct SourceChars<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    col: usize,
+}
+
+impl<'a> SourceChars<'a> {
+    fn new(text: &'a str) -> SourceChars<'a> {
+        SourceChars {
+            chars: text.chars().peekable(),
+            line: 1,
+            col: 1,
+        }
+    }
+
+    fn peek_char(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+}
+
+impl Iterator for SourceChars<'_> {
+    type Item = (char, usize, usize);
+
+    fn next(&mut self) -> Option<(char, usize, usize)> {
+        let c = self.chars.next()?;
+        let (line, col) = (self.line, self.col);
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some((c, line, col))
+    }
+}
+
+fn lex_string(chars: &mut SourceChars) -> Result<String, StutterError> {
+    let mut s = String::new();
+    while let Some((c, _, _)) = chars.next() {
+        match c {
+            '"' => return Ok(s),
+            '\\' => match chars.next().map(|(escaped, _, _)| escaped) {
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some('r') => s.push('\r'),
+                Some('0') => s.push('\0'),
+                Some('\\') => s.push('\\'),
+                Some('"') => s.push('"'),
+                Some(other) => {
+                    return Err(ErrorKind::Lex(format!(
+                        "invalid escape sequence in string: \\{}",
+                        other
+                    ))
+                    .into())
+                }
+                None => break,
+            },
+            _ => s.push(c),
+        }
+    }
+    Err(ErrorKind::Lex(String::from("unterminated string literal")).into())
+}
+
+fn push_token(tokens: &mut Vec<(Token, Span)>, tok: &mut String, span: &Span) {
+    if !tok.is_empty() {
+        tokens.push((to_token(&std::mem::take(tok)), span.clone()));
+    }
+}
+
+pub(crate) fn lex(
+    source: &Rc<Source>,
+) -> Result<Vec<(Token, Span)>, StutterError> {
+    let mut tokens = Vec::new();
+    let mut tok = String::new();
+    let mut tok_span = Span::default();
+    let mut in_comment = false;
+    let mut chars = SourceChars::new(&source.text);
+    while let Some((c, line, col)) = chars.next() {
+        if in_comment {
+            if c == '\n' {
+                in_comment = false;
+            }
+            continue;
+        }
+        let span = Span {
+            source: source.clone(),
+            line,
+            col,
+        };
+        match c {
+            ';' => {
+                push_token(&mut tokens, &mut tok, &tok_span);
+                in_comment = true;
+            }
+
+            '(' | ')' | '{' | '}' => {
+                push_token(&mut tokens, &mut tok, &tok_span);
+                tokens.push((to_token(&c.to_string()), span));
+            }
+
+            '\'' | '`' | ',' => {
+                push_token(&mut tokens, &mut tok, &tok_span);
+                let mut prefix = c.to_string();
+                if c == ',' && chars.peek_char() == Some('@') {
+                    chars.next();
+                    prefix.push('@');
+                }
+                tokens.push((to_token(&prefix), span));
+            }
+
+            '"' => {
+                push_token(&mut tokens, &mut tok, &tok_span);
+                let s = lex_string(&mut chars).map_err(|e| e.at(&span))?;
+                tokens.push((Token::Str(s), span));
+            }
+
+            '#' if tok.is_empty() && chars.peek_char() == Some('{') => {
+                chars.next();
+                tokens.push((to_token(&String::from("#{")), span));
+            }
+
+            _ if c.is_whitespace() => {
+                push_token(&mut tokens, &mut tok, &tok_span);
+            }
+
+            _ => {
+                if tok.is_empty() {
+                    tok_span = span;
+                }
+                tok.push(c);
+            }
+        }
+    }
+    push_token(&mut tokens, &mut tok, &tok_span);
+    Ok(tokens)
+}
@@ -0,0 +1,44 @@
+// This file is part of Stutter.
+//
+// Stutter is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Stutter is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Stutter.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Stutter, a small functional lisp, as a library. Embed it by creating an
+//! `Interpreter` and handing it source text:
+//!
+//! ```
+//! use stutter::{FromStutter, Interpreter};
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.define("xs", vec![1i64, 2, 3]).unwrap();
+//! let total = interpreter.eval_str("(sum xs)").unwrap();
+//! assert_eq!(i64::from_stutter(&total).unwrap(), 6);
+//! ```
+
+mod collections;
+mod convert;
+mod error;
+mod eval;
+mod interpreter;
+mod lexer;
+mod macros;
+mod object;
+mod parser;
+
+pub use crate::convert::{FromStutter, IntoStutter};
+pub use crate::error::{ErrorKind, Source, Span, StutterError};
+pub use crate::interpreter::{Interpreter, Truthiness, STDLIB};
+pub use crate::object::{
+    Arity, Closure, MapKey, NativeFunction, StutterObject,
+};
+pub use crate::parser::Op;
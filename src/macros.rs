@@ -0,0 +1,308 @@
+// This file is part of Stutter.
+//
+// Stutter is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Stutter is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Stutter.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Code as data: converting between parse trees and Stutter values, and
+//! the macro machinery built on top of that (quasiquote, macro expansion
+//! and gensym).
+
+use crate::error::{ErrorKind, Span, StutterError};
+use crate::eval::{bind_params, eval, with_stack};
+use crate::lexer::{to_token, Token};
+use crate::object::{sorted_entries, sorted_members, Closure, StutterObject};
+use crate::parser::{
+    list_to_branch, op_to_string, token_to_op, too_deeply_nested, Op,
+    ParseTree, MAX_NESTING_DEPTH,
+};
+use im_rc::vector;
+use rpds::HashTrieMap;
+use std::cell::Cell;
+use std::collections::HashMap;
+
+thread_local! {
+    static GENSYM_COUNTER: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A fresh symbol for macros to bind without capturing the user's names.
+/// The `#:` prefix keeps it out of the way of anything written by hand.
+pub(crate) fn gensym() -> StutterObject {
+    let n = GENSYM_COUNTER.with(|counter| {
+        counter.set(counter.get() + 1);
+        counter.get()
+    });
+    StutterObject::Symbol(format!("#:g{}", n))
+}
+
+fn token_to_data(tok: &Token) -> Result<StutterObject, StutterError> {
+    match tok {
+        Token::Int(i) => Ok(StutterObject::Int(i.clone())),
+        Token::Real(f) => Ok(StutterObject::Real(*f)),
+        Token::Ratio(r) => Ok(StutterObject::Ratio(r.clone())),
+        Token::Bool(b) => Ok(StutterObject::Bool(*b)),
+        Token::Nil => Ok(StutterObject::Nil),
+        Token::Str(s) => Ok(StutterObject::Str(s.to_string())),
+        Token::Keyword(k) => Ok(StutterObject::Keyword(k.to_string())),
+        _ => Ok(StutterObject::Symbol(
+            op_to_string(&token_to_op(tok)?).to_string(),
+        )),
+    }
+}
+
+/// The symbol at the head of the list a branch was read from. Calls whose
+/// head is a list or a literal keep it among their arguments, and `()` has
+/// none.
+fn op_to_data(op: &Op, xs: &[ParseTree]) -> Option<StutterObject> {
+    match op {
+        Op::Call => None,
+        Op::List if xs.is_empty() => None,
+        _ => Some(StutterObject::Symbol(op_to_string(op).to_string())),
+    }
+}
+
+/// Turns code into the list of symbols and literals it was written as.
+pub(crate) fn tree_to_data(
+    tree: &ParseTree,
+) -> Result<StutterObject, StutterError> {
+    with_stack(|| match tree {
+        ParseTree::Leaf(tok, _) => token_to_data(tok),
+        ParseTree::Branch(op, xs, _) => {
+            let mut items: Vec<StutterObject> =
+                op_to_data(op, xs).into_iter().collect();
+            for x in xs.iter() {
+                items.push(tree_to_data(x)?);
+            }
+            Ok(StutterObject::List(items.into()))
+        }
+    })
+}
+
+/// Turns data back into code. Data has no position of its own, so the
+/// whole tree is attributed to `span`, typically the macro call that
+/// produced it.
+pub(crate) fn data_to_tree(
+    obj: &StutterObject,
+    span: &Span,
+) -> Result<ParseTree, StutterError> {
+    nested_data_to_tree(obj, span, 0)
+}
+
+/// `data_to_tree` for data found `depth` levels into the code being built,
+/// which may only nest as deeply as code that was read.
+fn nested_data_to_tree(
+    obj: &StutterObject,
+    span: &Span,
+    depth: usize,
+) -> Result<ParseTree, StutterError> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err(ErrorKind::Syntax(too_deeply_nested()).into());
+    }
+    with_stack(|| match obj {
+        StutterObject::List(items) => match items.head() {
+            None => Ok(ParseTree::Branch(Op::List, Vec::new(), span.clone())),
+            Some(head) => {
+                let head = nested_data_to_tree(head, span, depth + 1)?;
+                let xs = items
+                    .iter()
+                    .skip(1)
+                    .map(|item| nested_data_to_tree(item, span, depth + 1))
+                    .collect::<Result<Vec<ParseTree>, StutterError>>()?;
+                list_to_branch(head, xs, span.clone())
+            }
+        },
+        StutterObject::Map(map) => {
+            let mut xs = Vec::new();
+            for (key, value) in sorted_entries(map) {
+                xs.push(nested_data_to_tree(
+                    &key.to_object(),
+                    span,
+                    depth + 1,
+                )?);
+                xs.push(nested_data_to_tree(value, span, depth + 1)?);
+            }
+            Ok(ParseTree::Branch(Op::HashMap, xs, span.clone()))
+        }
+        StutterObject::Set(set) => {
+            let xs = sorted_members(set)
+                .into_iter()
+                .map(|member| {
+                    nested_data_to_tree(&member.to_object(), span, depth + 1)
+                })
+                .collect::<Result<Vec<ParseTree>, StutterError>>()?;
+            Ok(ParseTree::Branch(Op::HashSet, xs, span.clone()))
+        }
+        _ => {
+            let tok = match obj {
+                StutterObject::Int(i) => Token::Int(i.clone()),
+                StutterObject::Real(f) => Token::Real(*f),
+                StutterObject::Ratio(r) => Token::Ratio(r.clone()),
+                StutterObject::Bool(b) => Token::Bool(*b),
+                StutterObject::Nil => Token::Nil,
+                StutterObject::Str(s) => Token::Str(s.clone()),
+                StutterObject::Keyword(k) => Token::Keyword(k.clone()),
+                StutterObject::Symbol(s) | StutterObject::Id(s) => to_token(s),
+                StutterObject::Builtin(op) => {
+                    to_token(&op_to_string(op).to_string())
+                }
+                _ => return Err(cannot_be_code(obj)),
+            };
+            Ok(ParseTree::Leaf(tok, span.clone()))
+        }
+    })
+}
+
+fn cannot_be_code(obj: &StutterObject) -> StutterError {
+    ErrorKind::Syntax(format!("{} cannot be used as code", obj)).into()
+}
+
+fn expect_one<'a>(
+    form: &str,
+    xs: &'a [ParseTree],
+) -> Result<&'a ParseTree, StutterError> {
+    match xs {
+        [x] => Ok(x),
+        _ => {
+            Err(ErrorKind::Syntax(format!("expecting: ({} ITEM)", form))
+                .into())
+        }
+    }
+}
+
+/// Evaluates the template `tree` of a quasiquote `depth` levels deep.
+/// Only unquotes at depth 1 are evaluated; nested ones are kept as data so
+/// that macro-writing macros work.
+pub(crate) fn quasiquote(
+    tree: &ParseTree,
+    depth: usize,
+    env: &HashTrieMap<String, StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    with_stack(|| match tree {
+        ParseTree::Leaf(tok, _) => token_to_data(tok),
+        ParseTree::Branch(Op::Unquote, xs, _) => {
+            let x = expect_one("unquote", xs)?;
+            if depth == 1 {
+                eval(x, env, global_env)
+            } else {
+                let inner = quasiquote(x, depth - 1, env, global_env)?;
+                Ok(StutterObject::List(vector![
+                    StutterObject::Symbol(String::from("unquote")),
+                    inner,
+                ]))
+            }
+        }
+        ParseTree::Branch(Op::UnquoteSplicing, xs, span) => {
+            let x = expect_one("unquote-splicing", xs)?;
+            if depth == 1 {
+                return Err(StutterError::new(
+                    ErrorKind::Syntax(String::from(
+                        "',@' can only be used inside a list",
+                    )),
+                    span,
+                ));
+            }
+            let inner = quasiquote(x, depth - 1, env, global_env)?;
+            Ok(StutterObject::List(vector![
+                StutterObject::Symbol(String::from("unquote-splicing")),
+                inner,
+            ]))
+        }
+        ParseTree::Branch(op, xs, _) => {
+            let depth = match op {
+                Op::Quasiquote => depth + 1,
+                _ => depth,
+            };
+            let mut items: Vec<StutterObject> =
+                op_to_data(op, xs).into_iter().collect();
+            for x in xs.iter() {
+                match x {
+                    ParseTree::Branch(Op::UnquoteSplicing, ys, span)
+                        if depth == 1 =>
+                    {
+                        let y = expect_one("unquote-splicing", ys)?;
+                        match eval(y, env, global_env)? {
+                            StutterObject::List(ref spliced) => {
+                                items.extend(spliced.iter().cloned())
+                            }
+                            StutterObject::Nil => (),
+                            other => {
+                                return Err(StutterError::new(
+                                    ErrorKind::Type(format!(
+                                        "',@' expecting a list, got {}",
+                                        other
+                                    )),
+                                    span,
+                                ))
+                            }
+                        }
+                    }
+                    _ => items.push(quasiquote(x, depth, env, global_env)?),
+                }
+            }
+            Ok(StutterObject::List(items.into()))
+        }
+    })
+}
+
+/// Runs a macro's body with its parameters bound to the unevaluated
+/// argument forms, returning the code it expands to as data.
+pub(crate) fn call_macro(
+    closure: &Closure,
+    args: Vec<StutterObject>,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    let name = closure.name.as_deref().unwrap_or("<macro>");
+    let label = format!("macro {}", name);
+    let env =
+        bind_params(closure, &label, closure.env.clone(), args, global_env)?;
+    eval(&closure.body, &env, global_env).map_err(|e| e.in_frame(name))
+}
+
+/// Expands `form` once if it is a call to a macro, or returns `None`.
+fn expand_once(
+    form: &StutterObject,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<Option<StutterObject>, StutterError> {
+    let (head, args) = match form {
+        StutterObject::List(items) => match items.head() {
+            Some(StutterObject::Symbol(head)) => (head, items.skip(1)),
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    match global_env.get(head) {
+        Some(StutterObject::Macro(closure)) => {
+            let closure = closure.clone();
+            let args = args.into_iter().collect();
+            Ok(Some(call_macro(&closure, args, global_env)?))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// `macroexpand-1` expands the outermost macro call in `form` once;
+/// `macroexpand` keeps going until the head is no longer a macro.
+pub(crate) fn macroexpand(
+    form: &StutterObject,
+    repeat: bool,
+    global_env: &mut HashMap<String, StutterObject>,
+) -> Result<StutterObject, StutterError> {
+    let mut form = form.clone();
+    while let Some(expanded) = expand_once(&form, global_env)? {
+        form = expanded;
+        if !repeat {
+            break;
+        }
+    }
+    Ok(form)
+}
@@ -0,0 +1,590 @@
+// This file is part of Stutter.
+//
+// Stutter is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Stutter is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Stutter.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The values Stutter programs compute with.
+
+use crate::error::{ErrorKind, StutterError};
+use crate::eval::with_stack;
+use crate::parser::{op_to_string, Op, ParseTree, MAX_NESTING_DEPTH};
+use im_rc::Vector;
+use num_bigint::{BigInt, ToBigInt};
+use num_rational::BigRational;
+use num_traits::cast::ToPrimitive;
+use rpds::{HashTrieMap, HashTrieSet};
+use std::fmt;
+use std::rc::Rc;
+
+/// A Stutter value.
+#[derive(Clone)]
+pub enum StutterObject {
+    Nil,
+    Int(BigInt),
+    Real(f64),
+    Ratio(BigRational),
+    Bool(bool),
+    Str(String),
+    Id(String),
+    Symbol(String),
+    Keyword(String),
+    Lambda(Rc<Closure>),
+    Macro(Rc<Closure>),
+    Builtin(Op),
+    Native(Rc<NativeFunction>),
+    List(Vector<StutterObject>),
+    Map(HashTrieMap<MapKey, StutterObject>),
+    Set(HashTrieSet<MapKey>),
+}
+
+/// The values that can be used as map keys and set members: those with a
+/// well-defined notion of equality. Reals (because of NaN) and functions
+/// can't be.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MapKey {
+    Bool(bool),
+    Int(BigInt),
+    Ratio(BigRational),
+    Str(String),
+    Keyword(String),
+    Symbol(String),
+    List(Vec<MapKey>),
+}
+
+/// `nil` stands for the empty list, so the two are equal. Lists and maps
+/// can nest deeper than the stack can recurse, since they are built up at
+/// run time, so comparing, printing and freeing them goes through
+/// `with_stack` at every level.
+impl PartialEq for StutterObject {
+    fn eq(&self, other: &StutterObject) -> bool {
+        match (self, other) {
+            (StutterObject::Nil, StutterObject::Nil) => true,
+            (StutterObject::Nil, StutterObject::List(l))
+            | (StutterObject::List(l), StutterObject::Nil) => l.is_empty(),
+            (StutterObject::Int(a), StutterObject::Int(b)) => a == b,
+            (StutterObject::Real(a), StutterObject::Real(b)) => a == b,
+            (StutterObject::Ratio(a), StutterObject::Ratio(b)) => a == b,
+            (StutterObject::Bool(a), StutterObject::Bool(b)) => a == b,
+            (StutterObject::Str(a), StutterObject::Str(b))
+            | (StutterObject::Id(a), StutterObject::Id(b))
+            | (StutterObject::Symbol(a), StutterObject::Symbol(b))
+            | (StutterObject::Keyword(a), StutterObject::Keyword(b)) => a == b,
+            (StutterObject::Lambda(a), StutterObject::Lambda(b))
+            | (StutterObject::Macro(a), StutterObject::Macro(b)) => a == b,
+            (StutterObject::Builtin(a), StutterObject::Builtin(b)) => a == b,
+            (StutterObject::Native(a), StutterObject::Native(b)) => a == b,
+            (StutterObject::List(a), StutterObject::List(b)) => {
+                with_stack(|| a == b)
+            }
+            (StutterObject::Map(a), StutterObject::Map(b)) => {
+                with_stack(|| a == b)
+            }
+            (StutterObject::Set(a), StutterObject::Set(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for StutterObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        with_stack(|| match self {
+            StutterObject::Nil => f.write_str("Nil"),
+            StutterObject::Int(n) => f.debug_tuple("Int").field(n).finish(),
+            StutterObject::Real(r) => f.debug_tuple("Real").field(r).finish(),
+            StutterObject::Ratio(r) => {
+                f.debug_tuple("Ratio").field(r).finish()
+            }
+            StutterObject::Bool(b) => f.debug_tuple("Bool").field(b).finish(),
+            StutterObject::Str(s) => f.debug_tuple("Str").field(s).finish(),
+            StutterObject::Id(s) => f.debug_tuple("Id").field(s).finish(),
+            StutterObject::Symbol(s) => {
+                f.debug_tuple("Symbol").field(s).finish()
+            }
+            StutterObject::Keyword(k) => {
+                f.debug_tuple("Keyword").field(k).finish()
+            }
+            StutterObject::Lambda(c) => {
+                f.debug_tuple("Lambda").field(c).finish()
+            }
+            StutterObject::Macro(c) => {
+                f.debug_tuple("Macro").field(c).finish()
+            }
+            StutterObject::Builtin(op) => {
+                f.debug_tuple("Builtin").field(op).finish()
+            }
+            StutterObject::Native(n) => {
+                f.debug_tuple("Native").field(n).finish()
+            }
+            StutterObject::List(l) => f.debug_tuple("List").field(l).finish(),
+            StutterObject::Map(m) => f.debug_tuple("Map").field(m).finish(),
+            StutterObject::Set(s) => f.debug_tuple("Set").field(s).finish(),
+        })
+    }
+}
+
+impl Drop for StutterObject {
+    fn drop(&mut self) {
+        match self {
+            StutterObject::List(items) if !items.is_empty() => {
+                let items = std::mem::take(items);
+                with_stack(move || drop(items));
+            }
+            StutterObject::Map(map) if !map.is_empty() => {
+                let map = std::mem::take(map);
+                with_stack(move || drop(map));
+            }
+            _ => (),
+        }
+    }
+}
+
+impl MapKey {
+    pub(crate) fn from_object(
+        obj: &StutterObject,
+    ) -> Result<MapKey, StutterError> {
+        MapKey::nested_from_object(obj, 0)
+    }
+
+    /// `from_object` for a value found `depth` lists into the key. Keys are
+    /// hashed and compared recursively, so they may only nest as deeply as
+    /// code.
+    fn nested_from_object(
+        obj: &StutterObject,
+        depth: usize,
+    ) -> Result<MapKey, StutterError> {
+        with_stack(|| match obj {
+            StutterObject::Bool(b) => Ok(MapKey::Bool(*b)),
+            StutterObject::Nil => Ok(MapKey::List(Vec::new())),
+            StutterObject::Int(n) => Ok(MapKey::Int(n.clone())),
+            StutterObject::Ratio(r) => Ok(MapKey::Ratio(r.clone())),
+            StutterObject::Str(s) => Ok(MapKey::Str(s.clone())),
+            StutterObject::Keyword(k) => Ok(MapKey::Keyword(k.clone())),
+            StutterObject::Symbol(s) => Ok(MapKey::Symbol(s.clone())),
+            StutterObject::List(_) if depth == MAX_NESTING_DEPTH => {
+                Err(ErrorKind::Value(format!(
+                    "map keys nested more than {} deep",
+                    MAX_NESTING_DEPTH
+                ))
+                .into())
+            }
+            StutterObject::List(items) => Ok(MapKey::List(
+                items
+                    .iter()
+                    .map(|item| MapKey::nested_from_object(item, depth + 1))
+                    .collect::<Result<Vec<MapKey>, StutterError>>()?,
+            )),
+            _ => Err(ErrorKind::Type(format!(
+                "{} cannot be used as a map key",
+                obj
+            ))
+            .into()),
+        })
+    }
+
+    pub(crate) fn to_object(&self) -> StutterObject {
+        match self {
+            MapKey::Bool(b) => StutterObject::Bool(*b),
+            MapKey::Int(n) => StutterObject::Int(n.clone()),
+            MapKey::Ratio(r) => StutterObject::Ratio(r.clone()),
+            MapKey::Str(s) => StutterObject::Str(s.clone()),
+            MapKey::Keyword(k) => StutterObject::Keyword(k.clone()),
+            MapKey::Symbol(s) => StutterObject::Symbol(s.clone()),
+            MapKey::List(items) => StutterObject::List(
+                items.iter().map(MapKey::to_object).collect(),
+            ),
+        }
+    }
+}
+
+/// The entries of `map` ordered by key, which is how maps print and the
+/// order `keys` and `vals` return them in.
+pub(crate) fn sorted_entries(
+    map: &HashTrieMap<MapKey, StutterObject>,
+) -> Vec<(&MapKey, &StutterObject)> {
+    let mut entries: Vec<(&MapKey, &StutterObject)> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    entries
+}
+
+/// The members of `set` in order, which is how sets print.
+pub(crate) fn sorted_members(set: &HashTrieSet<MapKey>) -> Vec<&MapKey> {
+    let mut members: Vec<&MapKey> = set.iter().collect();
+    members.sort();
+    members
+}
+
+/// A lambda together with the environment it was created in. Calling it
+/// evaluates the body in that captured scope rather than the caller's, and
+/// a named closure (a `lambda` form bound by `let` or `def`) can also see
+/// itself so that it may recurse. A closure bound by `letrec` can see everything
+/// bound alongside it too.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Closure {
+    pub(crate) params: Rc<Params>,
+    pub(crate) body: Rc<ParseTree>,
+    pub(crate) env: HashTrieMap<String, StutterObject>,
+    pub(crate) name: Option<String>,
+    pub(crate) group: Option<Rc<RecursiveGroup>>,
+}
+
+/// Every binding one `letrec` makes, with its lambdas as they were before
+/// they joined the group. A member is called with these layered over the
+/// scope it captured, its fellow lambdas joined to the group again, much
+/// as a named closure is handed itself. Nothing in here refers back to the
+/// group, so the lambdas are freed like any others.
+#[derive(Debug, PartialEq)]
+pub(crate) struct RecursiveGroup {
+    bindings: Vec<(String, StutterObject)>,
+}
+
+impl RecursiveGroup {
+    pub(crate) fn new(bindings: Vec<(String, StutterObject)>) -> Self {
+        RecursiveGroup { bindings }
+    }
+
+    /// `env` with the group's bindings over it.
+    pub(crate) fn bind(
+        self: &Rc<Self>,
+        mut env: HashTrieMap<String, StutterObject>,
+    ) -> HashTrieMap<String, StutterObject> {
+        for (name, value) in self.bindings.iter() {
+            let value = match value {
+                StutterObject::Lambda(closure) => {
+                    StutterObject::Lambda(Rc::new(closure.in_group(self)))
+                }
+                _ => value.clone(),
+            };
+            env = env.insert(name.to_string(), value);
+        }
+        env
+    }
+}
+
+impl Closure {
+    fn named(&self, name: &str) -> Closure {
+        Closure {
+            name: Some(name.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// The closure as a member of `group`.
+    pub(crate) fn in_group(&self, group: &Rc<RecursiveGroup>) -> Closure {
+        Closure {
+            group: Some(group.clone()),
+            ..self.clone()
+        }
+    }
+}
+
+/// A lambda's parameter list:
+/// `(a b &optional (c DEFAULT) d &rest more &key (e DEFAULT) f)`. An
+/// optional or keyword parameter without a default is `Nil` when left out.
+/// Keyword arguments follow the positional ones, as in `(g 1 2 :e 3)`, and
+/// don't count towards the arity.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub(crate) struct Params {
+    pub(crate) required: Vec<String>,
+    pub(crate) optional: Vec<(String, Option<ParseTree>)>,
+    pub(crate) rest: Option<String>,
+    pub(crate) keys: Vec<(String, Option<ParseTree>)>,
+}
+
+impl Params {
+    pub(crate) fn arity(&self) -> Arity {
+        let required = self.required.len();
+        match (&self.rest, self.optional.len()) {
+            (Some(_), _) => Arity::AtLeast(required),
+            (None, 0) => Arity::Exact(required),
+            (None, optional) => Arity::Between(required, required + optional),
+        }
+    }
+}
+
+/// How many arguments a function accepts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    Between(usize, usize),
+}
+
+impl Arity {
+    fn accepts(&self, n: usize) -> bool {
+        match self {
+            Arity::Exact(want) => n == *want,
+            Arity::AtLeast(want) => n >= *want,
+            Arity::Between(min, max) => *min <= n && n <= *max,
+        }
+    }
+
+    /// Errors with "NAME expecting ARITY, got N" unless `n` arguments are
+    /// allowed.
+    pub(crate) fn check(
+        &self,
+        name: &str,
+        n: usize,
+    ) -> Result<(), StutterError> {
+        if self.accepts(n) {
+            Ok(())
+        } else {
+            Err(ErrorKind::Arity(format!(
+                "{} expecting {}, got {}",
+                name, self, n
+            ))
+            .into())
+        }
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(n: usize) -> Arity {
+        Arity::Exact(n)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arity::Exact(1) => write!(f, "1 argument"),
+            Arity::Exact(n) => write!(f, "{} arguments", n),
+            Arity::AtLeast(1) => write!(f, "at least 1 argument"),
+            Arity::AtLeast(n) => write!(f, "at least {} arguments", n),
+            Arity::Between(min, max) => {
+                write!(f, "{} to {} arguments", min, max)
+            }
+        }
+    }
+}
+
+type NativeFn =
+    dyn Fn(&[StutterObject]) -> Result<StutterObject, StutterError>;
+
+/// A Rust function registered with `Interpreter::register_fn`. It is called
+/// with its arguments already evaluated, once they have been checked
+/// against its arity.
+pub struct NativeFunction {
+    pub(crate) name: String,
+    pub(crate) arity: Arity,
+    pub(crate) func: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub(crate) fn call(
+        &self,
+        args: &[StutterObject],
+    ) -> Result<StutterObject, StutterError> {
+        self.arity.check(&self.name, args.len())?;
+        (self.func)(args)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+/// Native functions have no structure to compare, so two are only equal if
+/// they are the same registration.
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &NativeFunction) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// Names the value of `expr` after the variable it is bound to, so that it
+/// can call itself by that name, if `expr` is a `lambda` form. A closure
+/// made anywhere else keeps the bindings it captured instead.
+pub(crate) fn name_lambda(
+    name: &str,
+    expr: &ParseTree,
+    value: StutterObject,
+) -> StutterObject {
+    match (expr, &value) {
+        (
+            ParseTree::Branch(Op::Func(form), _, _),
+            StutterObject::Lambda(closure),
+        ) if form == "lambda" && closure.name.is_none() => {
+            StutterObject::Lambda(Rc::new(closure.named(name)))
+        }
+        _ => value,
+    }
+}
+
+impl fmt::Display for StutterObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        with_stack(|| match self {
+            StutterObject::Nil => write!(f, "Nil"),
+            StutterObject::Int(i) => write!(f, "{}", i),
+            StutterObject::Ratio(r) => write!(f, "{}", r),
+            StutterObject::Real(r) => {
+                let as_string = r.to_string();
+                if !as_string.contains(".") {
+                    write!(f, "{}.0", as_string)
+                } else {
+                    write!(f, "{}", as_string)
+                }
+            }
+            StutterObject::Bool(b) => write!(
+                f,
+                "{}",
+                if *b {
+                    String::from("True")
+                } else {
+                    String::from("False")
+                }
+            ),
+            StutterObject::Str(s) => write!(f, "\"{}\"", escape_string(s)),
+            StutterObject::Id(s) => write!(f, "{}", s),
+            StutterObject::Symbol(s) => write!(f, "{}", s),
+            StutterObject::Keyword(k) => write!(f, ":{}", k),
+            StutterObject::Lambda(_closure) => write!(f, "<lambda>"),
+            StutterObject::Macro(closure) => {
+                write!(f, "<macro {}>", closure.name.as_deref().unwrap_or("?"))
+            }
+            StutterObject::Builtin(op) => {
+                write!(f, "<builtin {}>", op_to_string(op))
+            }
+            StutterObject::Native(native) => {
+                write!(f, "<native {}>", native.name)
+            }
+            StutterObject::List(vec) => {
+                let mut string_vec = Vec::new();
+                for item in vec {
+                    string_vec.push(format!("{}", item));
+                }
+                let string = string_vec.join(" ");
+                write!(f, "({})", string)
+            }
+            StutterObject::Map(map) => {
+                let mut string_vec = Vec::new();
+                for (key, value) in sorted_entries(map) {
+                    string_vec.push(format!("{} {}", key.to_object(), value));
+                }
+                write!(f, "{{{}}}", string_vec.join(" "))
+            }
+            StutterObject::Set(set) => {
+                let string_vec: Vec<String> = sorted_members(set)
+                    .into_iter()
+                    .map(|member| member.to_object().to_string())
+                    .collect();
+                write!(f, "#{{{}}}", string_vec.join(" "))
+            }
+        })
+    }
+}
+
+fn escape_string(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub(crate) fn bigint_to_f64(n: &BigInt) -> Result<f64, StutterError> {
+    let opt_f = n.to_f64();
+    match opt_f {
+        Some(f) => Ok(f),
+        None => Err(ErrorKind::Value(String::from(
+            "failed to represent BigInt as f64",
+        ))
+        .into()),
+    }
+}
+
+pub(crate) fn ratio_to_f64(r: &BigRational) -> Result<f64, StutterError> {
+    match r.to_f64() {
+        Some(f) => Ok(f),
+        None => Err(ErrorKind::Value(String::from(
+            "failed to represent ratio as f64",
+        ))
+        .into()),
+    }
+}
+
+/// The value of an exact fraction: an `Int` when it is a whole number, so
+/// that `(/ 6 3)` is `2` rather than `2/1`.
+pub(crate) fn rational_to_object(r: BigRational) -> StutterObject {
+    if r.is_integer() {
+        StutterObject::Int(r.to_integer())
+    } else {
+        StutterObject::Ratio(r)
+    }
+}
+
+pub(crate) fn bigint_to_usize(n: &BigInt) -> Result<usize, StutterError> {
+    let opt_usize = n.to_usize();
+    match opt_usize {
+        Some(us) => Ok(us),
+        None => Err(ErrorKind::Value(format!(
+            "failed to represent BigInt as usize: {:?}",
+            n
+        ))
+        .into()),
+    }
+}
+
+pub(crate) fn bigint_to_i64(n: &BigInt) -> Result<i64, StutterError> {
+    let opt_usize = n.to_i64();
+    match opt_usize {
+        Some(us) => Ok(us),
+        None => Err(ErrorKind::Value(format!(
+            "failed to represent BigInt as i64: {:?}",
+            n
+        ))
+        .into()),
+    }
+}
+
+pub(crate) fn usize_to_bigint(n: usize) -> Result<BigInt, StutterError> {
+    let opt_bi = n.to_bigint();
+    match opt_bi {
+        Some(bi) => Ok(bi),
+        None => Err(ErrorKind::Value(String::from(
+            "failed to represent usize as BigInt",
+        ))
+        .into()),
+    }
+}
+
+pub(crate) fn i64_to_bigint(n: i64) -> Result<BigInt, StutterError> {
+    let opt_bi = n.to_bigint();
+    match opt_bi {
+        Some(bi) => Ok(bi),
+        None => Err(ErrorKind::Value(String::from(
+            "failed to represent i64 as BigInt",
+        ))
+        .into()),
+    }
+}
+
+pub(crate) fn f64_to_bigint(n: f64) -> Result<BigInt, StutterError> {
+    let opt_bi = n.to_bigint();
+    match opt_bi {
+        Some(bi) => Ok(bi),
+        None => Err(ErrorKind::Value(String::from(
+            "failed to represent f64 as BigInt",
+        ))
+        .into()),
+    }
+}
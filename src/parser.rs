@@ -0,0 +1,478 @@
+// This file is part of Stutter.
+//
+// Stutter is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Stutter is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Stutter.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Builds parse trees out of the token stream.
+
+use crate::error::{ErrorKind, Span, StutterError};
+use crate::lexer::Token;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    IntDiv,
+    Mod,
+    Pow,
+    Gt,
+    Lt,
+    Eq,
+    Gte,
+    Lte,
+    Let,
+    Def,
+    List,
+    Index,
+    Drop,
+    Quote,
+    Append,
+    Range,
+    Cat,
+    Len,
+    Take,
+    If,
+    ToReal,
+    ToInt,
+    StrLen,
+    Substr,
+    StrCat,
+    Split,
+    Join,
+    Upper,
+    Lower,
+    Trim,
+    Contains,
+    StartsWith,
+    ToStr,
+    ParseInt,
+    Raise,
+    Print,
+    Defmacro,
+    Quasiquote,
+    Unquote,
+    UnquoteSplicing,
+    MacroExpand,
+    MacroExpand1,
+    Gensym,
+    Eval,
+    And,
+    Or,
+    Not,
+    Cond,
+    When,
+    Unless,
+    Case,
+    Begin,
+    Letrec,
+    HashMap,
+    Get,
+    Assoc,
+    Dissoc,
+    Keys,
+    Vals,
+    HasKey,
+    Merge,
+    Update,
+    HashSet,
+    ToSet,
+    Union,
+    Intersection,
+    Difference,
+    IsSubset,
+    IsMember,
+    Apply,
+    IsList,
+    Cons,
+    First,
+    Rest,
+    IsEmpty,
+    IsPair,
+    Numerator,
+    Denominator,
+    Func(String),
+    Call,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum ParseTree {
+    Leaf(Token, Span),
+    Branch(Op, Vec<ParseTree>, Span),
+}
+
+impl ParseTree {
+    pub(crate) fn span(&self) -> &Span {
+        match self {
+            ParseTree::Leaf(_, span) => span,
+            ParseTree::Branch(_, _, span) => span,
+        }
+    }
+}
+
+/// How many brackets and reader prefixes may be open at once. This keeps
+/// every tree shallow enough to clone and drop on an ordinary thread stack.
+pub(crate) const MAX_NESTING_DEPTH: usize = 1_000;
+
+/// What went wrong when code nests past `MAX_NESTING_DEPTH`.
+pub(crate) fn too_deeply_nested() -> String {
+    format!("expressions nested more than {} deep", MAX_NESTING_DEPTH)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Production {
+    Tree(ParseTree),
+    Tok(Token, Span),
+}
+
+pub(crate) fn token_to_op(tok: &Token) -> Result<Op, StutterError> {
+    match tok {
+        Token::Plus => Ok(Op::Add),
+        Token::Minus => Ok(Op::Sub),
+        Token::Times => Ok(Op::Mul),
+        Token::Slash => Ok(Op::Div),
+        Token::DoubleSlash => Ok(Op::IntDiv),
+        Token::Percent => Ok(Op::Mod),
+        Token::Pow => Ok(Op::Pow),
+        Token::Gt => Ok(Op::Gt),
+        Token::Lt => Ok(Op::Lt),
+        Token::Eq => Ok(Op::Eq),
+        Token::Gte => Ok(Op::Gte),
+        Token::Lte => Ok(Op::Lte),
+        Token::Let => Ok(Op::Let),
+        Token::Def => Ok(Op::Def),
+        Token::List => Ok(Op::List),
+        Token::Index => Ok(Op::Index),
+        Token::Take => Ok(Op::Take),
+        Token::If => Ok(Op::If),
+        Token::Drop => Ok(Op::Drop),
+        Token::Quote => Ok(Op::Quote),
+        Token::Append => Ok(Op::Append),
+        Token::ToReal => Ok(Op::ToReal),
+        Token::ToInt => Ok(Op::ToInt),
+        Token::Range => Ok(Op::Range),
+        Token::Cat => Ok(Op::Cat),
+        Token::Len => Ok(Op::Len),
+        Token::Defmacro => Ok(Op::Defmacro),
+        Token::Quasiquote => Ok(Op::Quasiquote),
+        Token::Unquote => Ok(Op::Unquote),
+        Token::UnquoteSplicing => Ok(Op::UnquoteSplicing),
+        Token::And => Ok(Op::And),
+        Token::Or => Ok(Op::Or),
+        Token::Cond => Ok(Op::Cond),
+        Token::When => Ok(Op::When),
+        Token::Unless => Ok(Op::Unless),
+        Token::Case => Ok(Op::Case),
+        Token::Begin => Ok(Op::Begin),
+        Token::Letrec => Ok(Op::Letrec),
+        Token::Id(s) => Ok(Op::Func(s.to_string())),
+        _ => Err(ErrorKind::Parse(format!("invalid op: {:?}", tok)).into()),
+    }
+}
+
+pub(crate) fn op_to_string(op: &Op) -> &str {
+    match op {
+        Op::Add => "+",
+        Op::Sub => "-",
+        Op::Mul => "*",
+        Op::Div => "/",
+        Op::IntDiv => "//",
+        Op::Mod => "%",
+        Op::Pow => "pow",
+        Op::Gt => ">",
+        Op::Lt => "<",
+        Op::Eq => "=",
+        Op::Gte => ">=",
+        Op::Lte => "<=",
+        Op::Let => "let",
+        Op::Def => "def",
+        Op::List => "list",
+        Op::Index => "index",
+        Op::Drop => "drop",
+        Op::Quote => "quote",
+        Op::Append => "append",
+        Op::Range => "range",
+        Op::Cat => "cat",
+        Op::Len => "len",
+        Op::Take => "take",
+        Op::If => "if",
+        Op::ToReal => "real",
+        Op::ToInt => "int",
+        Op::StrLen => "str-len",
+        Op::Substr => "substr",
+        Op::StrCat => "str-cat",
+        Op::Split => "split",
+        Op::Join => "join",
+        Op::Upper => "upper",
+        Op::Lower => "lower",
+        Op::Trim => "trim",
+        Op::Contains => "contains",
+        Op::StartsWith => "starts-with",
+        Op::ToStr => "to-string",
+        Op::ParseInt => "parse-int",
+        Op::Raise => "error",
+        Op::Print => "print",
+        Op::Defmacro => "defmacro",
+        Op::Quasiquote => "quasiquote",
+        Op::Unquote => "unquote",
+        Op::UnquoteSplicing => "unquote-splicing",
+        Op::MacroExpand => "macroexpand",
+        Op::MacroExpand1 => "macroexpand-1",
+        Op::Gensym => "gensym",
+        Op::Eval => "eval",
+        Op::And => "and",
+        Op::Or => "or",
+        Op::Not => "not",
+        Op::Cond => "cond",
+        Op::When => "when",
+        Op::Unless => "unless",
+        Op::Case => "case",
+        Op::Begin => "begin",
+        Op::Letrec => "letrec",
+        Op::HashMap => "hash-map",
+        Op::Get => "get",
+        Op::Assoc => "assoc",
+        Op::Dissoc => "dissoc",
+        Op::Keys => "keys",
+        Op::Vals => "vals",
+        Op::HasKey => "contains?",
+        Op::Merge => "merge",
+        Op::Update => "update",
+        Op::HashSet => "hash-set",
+        Op::ToSet => "set",
+        Op::Union => "union",
+        Op::Intersection => "intersection",
+        Op::Difference => "difference",
+        Op::IsSubset => "subset?",
+        Op::IsMember => "member?",
+        Op::Apply => "apply",
+        Op::IsList => "list?",
+        Op::Cons => "cons",
+        Op::First => "first",
+        Op::Rest => "rest",
+        Op::IsEmpty => "empty?",
+        Op::IsPair => "pair?",
+        Op::Numerator => "numerator",
+        Op::Denominator => "denominator",
+        Op::Func(name) => name,
+        Op::Call => "call",
+    }
+}
+
+/// The form a reader prefix such as `` ` `` abbreviates, if `tok` is one.
+fn prefix_op(tok: &Token) -> Option<Op> {
+    match tok {
+        Token::Apostrophe => Some(Op::Quote),
+        Token::Backquote => Some(Op::Quasiquote),
+        Token::Comma => Some(Op::Unquote),
+        Token::CommaAt => Some(Op::UnquoteSplicing),
+        _ => None,
+    }
+}
+
+fn dangling_prefix(tok: &Token, span: &Span) -> StutterError {
+    let prefix = match tok {
+        Token::Apostrophe => "'",
+        Token::Backquote => "`",
+        Token::Comma => ",",
+        _ => ",@",
+    };
+    StutterError::new(
+        ErrorKind::Parse(format!(
+            "syntax error, expecting an expression after '{}'",
+            prefix
+        )),
+        span,
+    )
+}
+
+/// Pushes a complete expression, first wrapping it in any reader prefixes
+/// waiting on the stack for it, so `'x` becomes `(quote x)` and `` `,x ``
+/// becomes `(quasiquote (unquote x))`. `open` counts the brackets and
+/// prefixes still waiting on the stack.
+fn push_datum(
+    stack: &mut Vec<Production>,
+    open: &mut usize,
+    mut tree: ParseTree,
+) {
+    while let Some(Production::Tok(tok, span)) = stack.last() {
+        let op = match prefix_op(tok) {
+            Some(op) => op,
+            None => break,
+        };
+        tree = ParseTree::Branch(op, vec![tree], span.clone());
+        stack.pop();
+        *open -= 1;
+    }
+    stack.push(Production::Tree(tree));
+}
+
+fn push_production(
+    stack: &mut Vec<Production>,
+    open: &mut usize,
+    mut list: Vec<ParseTree>,
+    span: Span,
+) -> Result<(), StutterError> {
+    let branch = match list.pop() {
+        Some(head) => {
+            list.reverse();
+            list_to_branch(head, list, span)?
+        }
+        None => ParseTree::Branch(Op::List, list, span),
+    };
+    push_datum(stack, open, branch);
+    Ok(())
+}
+
+/// The branch for a list starting with `head`: a call when the head is an
+/// expression or a literal, and otherwise the form or builtin it names.
+/// Quoted code goes back through here too, so it evaluates the same way.
+pub(crate) fn list_to_branch(
+    head: ParseTree,
+    mut args: Vec<ParseTree>,
+    span: Span,
+) -> Result<ParseTree, StutterError> {
+    match head {
+        ParseTree::Branch(_, _, _)
+        | ParseTree::Leaf(
+            Token::Int(_)
+            | Token::Real(_)
+            | Token::Ratio(_)
+            | Token::Bool(_)
+            | Token::Nil
+            | Token::Str(_)
+            | Token::Keyword(_),
+            _,
+        ) => {
+            args.insert(0, head);
+            Ok(ParseTree::Branch(Op::Call, args, span))
+        }
+        ParseTree::Leaf(op_tok, op_span) => {
+            let op = token_to_op(&op_tok).map_err(|e| e.at(&op_span))?;
+            Ok(ParseTree::Branch(op, args, span))
+        }
+    }
+}
+
+/// Pops the expressions read since the token among `opens` that the
+/// closing `close` at `span` matches, last first, along with that token and
+/// where it was.
+fn pop_until_open(
+    stack: &mut Vec<Production>,
+    opens: &[Token],
+    close: &str,
+    span: &Span,
+) -> Result<(Vec<ParseTree>, Token, Span), StutterError> {
+    let mut list: Vec<ParseTree> = Vec::new();
+    loop {
+        match stack.pop() {
+            Some(Production::Tok(t, s)) if opens.contains(&t) => {
+                return Ok((list, t, s))
+            }
+            Some(Production::Tok(t, s)) if prefix_op(&t).is_some() => {
+                return Err(dangling_prefix(&t, &s));
+            }
+            Some(Production::Tree(tree)) => list.push(tree),
+            _ => {
+                return Err(StutterError::new(
+                    ErrorKind::Parse(format!(
+                        "syntax error, unexpected '{}'",
+                        close
+                    )),
+                    span,
+                ))
+            }
+        }
+    }
+}
+
+pub(crate) fn parse(
+    tokens: &[(Token, Span)],
+) -> Result<Vec<ParseTree>, StutterError> {
+    let mut stack = Vec::new();
+    let mut open = 0;
+    for (tok, span) in tokens.iter() {
+        match tok {
+            Token::Rparen => {
+                let (list, _, lparen_span) =
+                    pop_until_open(&mut stack, &[Token::Lparen], ")", span)?;
+                open -= 1;
+                push_production(&mut stack, &mut open, list, lparen_span)?;
+            }
+            Token::Rbrace => {
+                let opens = [Token::Lbrace, Token::HashLbrace];
+                let (mut list, open_tok, lbrace_span) =
+                    pop_until_open(&mut stack, &opens, "}", span)?;
+                open -= 1;
+                list.reverse();
+                let op = match open_tok {
+                    Token::Lbrace => Op::HashMap,
+                    _ => Op::HashSet,
+                };
+                push_datum(
+                    &mut stack,
+                    &mut open,
+                    ParseTree::Branch(op, list, lbrace_span),
+                );
+            }
+            Token::Lparen
+            | Token::Lbrace
+            | Token::HashLbrace
+            | Token::Apostrophe
+            | Token::Backquote
+            | Token::Comma
+            | Token::CommaAt => {
+                if open == MAX_NESTING_DEPTH {
+                    return Err(StutterError::new(
+                        ErrorKind::Parse(format!(
+                            "syntax error, {}",
+                            too_deeply_nested()
+                        )),
+                        span,
+                    ));
+                }
+                open += 1;
+                stack.push(Production::Tok(tok.clone(), span.clone()))
+            }
+            _ => push_datum(
+                &mut stack,
+                &mut open,
+                ParseTree::Leaf(tok.clone(), span.clone()),
+            ),
+        }
+    }
+    stack
+        .into_iter()
+        .map(|production| match production {
+            Production::Tree(tree) => Ok(tree),
+            Production::Tok(Token::Lparen, span) => Err(StutterError::new(
+                ErrorKind::Parse(String::from("syntax error, unmatched '('")),
+                &span,
+            )),
+            Production::Tok(Token::Lbrace, span) => Err(StutterError::new(
+                ErrorKind::Parse(String::from("syntax error, unmatched '{'")),
+                &span,
+            )),
+            Production::Tok(Token::HashLbrace, span) => {
+                Err(StutterError::new(
+                    ErrorKind::Parse(String::from(
+                        "syntax error, unmatched '#{'",
+                    )),
+                    &span,
+                ))
+            }
+            Production::Tok(tok, span) => Err(dangling_prefix(&tok, &span)),
+        })
+        .collect()
+}
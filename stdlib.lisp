@@ -1,42 +1,20 @@
 (def True true)
 (def False false)
-(def head (lambda (l) (index 0 l)))
-(def tail (lambda (l) (drop 1 l)))
+(def head first)
+(def tail rest)
 (def empty (lambda (l) (= 0 (len l))))
-(def add (lambda (x y) (+ x y)))
-(def sub (lambda (x y) (- x y)))
-(def mul (lambda (x y) (* x y)))
-(def div (lambda (x y) (/ x y)))
-(def intdiv (lambda (x y) (// x y)))
-(def mod (lambda (x y) (% x y)))
+(def add +)
+(def sub -)
+(def mul *)
+(def div /)
+(def intdiv //)
+(def mod %)
 (def sqrt (lambda (x) (pow x 0.5)))
 (def last_n (lambda (n l) (drop (- (len l) n) l)))
 (def even (lambda (x) (= 0 (mod x 2))))
 (def odd (lambda (x) (= 1 (mod x 2))))
 (def last (lambda (l) (index (- (len l) 1) l)))
 
-(def or
-  (lambda (x y)
-    (if (x)
-      (True)
-      (if (y)
-        (True)
-        (False)))))
-
-(def and
-  (lambda (x y)
-    (if (x)
-      (if (y)
-        (True)
-        (False))
-      (False))))
-
-(def not
-  (lambda (x)
-    (if (x)
-      (False)
-      (True))))
-
 (def filter
   (lambda (f l)
     (let (rec-filter (lambda (f l acc)
@@ -60,11 +38,11 @@
 
 (def sum
   (lambda (l)
-    (reduce add l)))
+    (reduce + l)))
 
 (def product
   (lambda (l)
-    (reduce mul l)))
+    (reduce * l)))
 
 (def zip
   (lambda (l1 l2)
@@ -79,11 +57,10 @@
 
 (def collatz
   (lambda (x)
-    (if (= 1 x)
-      (list 1)
-      (if (= 0 (mod x 2))
-        (cat (list x) (collatz (// x 2)))
-        (cat (list x) (collatz (+ 1 (* 3 x))))))))
+    (cond
+      ((= 1 x) (list 1))
+      ((= 0 (mod x 2)) (cat (list x) (collatz (// x 2))))
+      (else (cat (list x) (collatz (+ 1 (* 3 x))))))))
 
 (def fibonacci
   (lambda (n)
@@ -98,17 +75,16 @@
 
 (def isprime
   (lambda (x)
-    (if (<= x 1)
-      False
-      (if (= x 2)
-        True
-          (let (rec-isprime (lambda (x acc max_num)
-            (if (>= acc max_num)
-              True
-              (if (= 0 (mod x acc))
-                False
-                (rec-isprime x (+ 1 acc) max_num)))))
-        (rec-isprime x 2 (+ 1 (sqrt x))))))))
+    (cond
+      ((<= x 1) False)
+      ((= x 2) True)
+      (else
+        (let (rec-isprime (lambda (x acc max_num)
+          (cond
+            ((>= acc max_num) True)
+            ((= 0 (mod x acc)) False)
+            (else (rec-isprime x (+ 1 acc) max_num)))))
+          (rec-isprime x 2 (+ 1 (sqrt x))))))))
 
 (def map
   (lambda (f l)
@@ -133,31 +109,36 @@
       a
       (gcd b (mod a b)))))
 
+(def arguments-or-list
+  (lambda (x more)
+    (if (and (empty more) (list? x))
+      x
+      (cat (list x) more))))
+
 (def max
-  (lambda (l)
-    (fold (lambda (a b)
-      (if (> a b)
-        a
-        b))
-     (head l) (tail l))))
+  (lambda (x &rest more)
+    (let (l (arguments-or-list x more))
+      (fold (lambda (a b)
+        (if (> a b)
+          a
+          b))
+       (head l) (tail l)))))
 
 (def min
-  (lambda (l)
-    (fold (lambda (a b)
-      (if (< a b)
-        a
-        b))
-     (head l) (tail l))))
+  (lambda (x &rest more)
+    (let (l (arguments-or-list x more))
+      (fold (lambda (a b)
+        (if (< a b)
+          a
+          b))
+       (head l) (tail l)))))
 
 (def deriv
-  (lambda (f x)
+  (lambda (f x &key (h 0.001))
     (/
-      (- (f (+ x 0.001))
+      (- (f (+ x h))
         (f x))
-      0.001)))
-
-(def print
-  (lambda (x) (x)))
+      h)))
 
 (def avg
   (lambda (l)
@@ -176,8 +157,7 @@
 (def factors
   (lambda (n)
     (filter
-      (lambda (x)
-        (let (quotient (/ n x)) (= (int quotient) quotient)))
+      (lambda (x) (= 0 (mod n x)))
       (range 1 (+ 1 n)))))
 
 (def factorial
@@ -187,12 +167,12 @@
       1)))
 
 (def sigma
-  (lambda (start end func)
-    (sum (map func (range start end)))))
+  (lambda (start end func &key (step 1))
+    (sum (map func (range start end :step step)))))
 
 (def exp
   (lambda (x)
-    (sigma 0 100 (lambda (k) (/ (pow x k) (factorial k))))))
+    (sigma 0 100 (lambda (k) (/ (pow x k) (real (factorial k)))))))
 
 (def is-perfect-number
   (lambda (x)
@@ -222,6 +202,4 @@
           True
           (any xs))))))
 
-(def length
-  (lambda (l)
-    (len l)))
+(def length len)
@@ -0,0 +1,88 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn begin_and_do_evaluate_in_order() {
+    let out = run_stutter("(begin (print \"a\") (print \"b\") 3)\n(do 1 2)\n");
+    assert_eq!(out, vec!["a", "b", "3", "2"]);
+    assert_eq!(eval_last("(begin)\n"), "Nil");
+}
+
+#[test]
+fn lambda_bodies_are_sequenced() {
+    let input = "(def f (lambda (x) (print \"got\" x) (* x 2)))\n(f 4)\n";
+    assert_eq!(run_stutter(input), vec!["Nil", "got 4", "8"]);
+}
+
+#[test]
+fn let_with_grouped_bindings_takes_a_body() {
+    let input = "(let ((a 1) (b 2)) (print \"sum\") (+ a b))\n";
+    assert_eq!(run_stutter(input), vec!["sum", "3"]);
+    assert_eq!(eval_last("(let () 5)\n"), "5");
+    assert_eq!(eval_last("(let (a 1) (b 2) (+ a b))\n"), "3");
+}
+
+#[test]
+fn def_shorthand_defines_a_function() {
+    let input = "(def (hyp a b) (def sq (lambda (x) (* x x))) \
+                 (+ (sq a) (sq b)))\n(hyp 3 4)\n";
+    assert_eq!(eval_last(input), "25");
+}
+
+#[test]
+fn internal_defs_are_local_to_the_body() {
+    let input = "(def (f x) (def y (* x 2)) (+ x y))\n(f 5)\ny\n";
+    let out = run_stutter(input);
+    assert_eq!(out[1], "15");
+    assert_eq!(out[2], "<stdin>:1:1: error: 'y' not in scope");
+}
+
+#[test]
+fn a_trailing_def_is_local_too() {
+    let input = "(def (f) (def inner 1))\n(f)\ninner\n";
+    let out = run_stutter(input);
+    assert_eq!(out[1], "Nil");
+    assert_eq!(out[2], "<stdin>:1:1: error: 'inner' not in scope");
+}
+
+#[test]
+fn a_begin_outside_a_body_defines_globals() {
+    let input = "(begin (def v 1) v)\nv\n(do (def z 1))\nz\n\
+                 (when true (def w 2))\nw\n";
+    assert_eq!(run_stutter(input), vec!["1", "1", "Nil", "1", "Nil", "2"]);
+    let input = "(defmacro defpair (a b) \
+                 `(begin (def ,a 1) (def ,b 2)))\n\
+                 (defpair one two)\n(+ one two)\n";
+    assert_eq!(eval_last(input), "3");
+}
+
+#[test]
+fn a_begin_or_macro_in_a_body_defines_locals() {
+    let input = "(defmacro defpair (a b) \
+                 `(begin (def ,a 1) (def ,b 2)))\n\
+                 (def (f) (defpair one two) (begin (def three 3)) \
+                 (+ one two three))\n(f)\none\nthree\n";
+    let out = run_stutter(input);
+    assert_eq!(out[2], "6");
+    assert_eq!(out[3], "<stdin>:1:1: error: 'one' not in scope");
+    assert_eq!(out[6], "<stdin>:1:1: error: 'three' not in scope");
+}
+
+#[test]
+fn internal_defs_can_call_each_other() {
+    let input = "(def (f n) \
+                 (def (ev n) (if (= n 0) true (od (- n 1)))) \
+                 (def (od n) (if (= n 0) false (ev (- n 1)))) \
+                 (list (ev n) (od n)))\n(f 4)\n(f 7)\n";
+    let out = run_stutter(input);
+    assert_eq!(out[1], "(True False)");
+    assert_eq!(out[2], "(False True)");
+}
+
+#[test]
+fn the_last_body_expression_is_a_tail_call() {
+    let input = "(def (count n) (def m (- n 1)) \
+                 (if (= n 0) \"done\" (count m)))\n(count 100000)\n";
+    assert_eq!(eval_last(input), "\"done\"");
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::eval_last;
+
+#[test]
+fn operators_are_values() {
+    assert_eq!(eval_last("+"), "<builtin +>");
+    assert_eq!(eval_last("(let (f *) (f 3 4))"), "12");
+    assert_eq!(eval_last("((if true + -) 5 3)"), "8");
+}
+
+#[test]
+fn builtins_passed_to_higher_order_functions() {
+    assert_eq!(eval_last("(fold + 0 (list 1 2 3))"), "6");
+    assert_eq!(eval_last("(map len (list (list 1) (list 1 2)))"), "(1 2)");
+    assert_eq!(eval_last("(map to-string (list 1 2))"), "(\"1\" \"2\")");
+}
+
+#[test]
+fn stdlib_aliases_still_work() {
+    assert_eq!(eval_last("(add 1 2)"), "3");
+    assert_eq!(eval_last("(mod 7 3)"), "1");
+    assert_eq!(eval_last("(length (list 1 2))"), "2");
+}
+
+#[test]
+fn list_operations_leave_their_inputs_alone() {
+    let input = "(def xs (range 0 5))\n\
+                 (list (append 5 xs) (take 2 xs) (drop 3 xs) (cat xs (list 9)) xs)\n";
+    assert_eq!(
+        eval_last(input),
+        "((0 1 2 3 4 5) (0 1) (3 4) (0 1 2 3 4 9) (0 1 2 3 4))"
+    );
+}
+
+#[test]
+fn long_lists_stay_fast() {
+    assert_eq!(
+        eval_last("(sum (map (lambda (x) (* 2 x)) (range 0 20000)))"),
+        "399980000"
+    );
+}
+
+#[test]
+fn library_builtin_names_can_be_variables() {
+    let input = "(def (f print eval) (list print eval))\n\
+                 (list (f 1 2) ((lambda (split) (* 2 split)) 4) (let (not 3) not))\n";
+    assert_eq!(eval_last(input), "((1 2) 8 3)");
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn immediately_invoked_lambda() {
+    assert_eq!(eval_last("((lambda (x) (* x x)) 4)"), "16");
+    assert_eq!(eval_last("((lambda () 5))"), "5");
+}
+
+#[test]
+fn function_returning_function() {
+    let program = "
+        (def make-adder (lambda (n) (lambda (x) (+ x n))))
+        ((make-adder 3) 4)
+    ";
+    assert_eq!(eval_last(program), "7");
+}
+
+#[test]
+fn nested_calls_in_operator_position() {
+    let program =
+        "((((lambda (a) (lambda (b) (lambda (c) (+ a b c)))) 1) 2) 3)";
+    assert_eq!(eval_last(program), "6");
+}
+
+#[test]
+fn calling_a_non_function_is_an_error() {
+    let out = run_stutter("(5 1 2)\n(\"abc\" 1)\n(map 'x (list 1))\n");
+    assert_eq!(out[0], "<stdin>:1:1: error: 5 is not a function");
+    assert_eq!(out[3], "<stdin>:1:1: error: \"abc\" is not a function");
+    assert!(out[6].contains("x is not a function"), "{}", out[6]);
+    assert_eq!(eval_last("(def x 5)\n(x)\n"), "5");
+}
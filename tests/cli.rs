@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn stutter(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_stutter"))
+        .args(args)
+        .output()
+        .expect("failed to start stutter")
+}
+
+fn write_script(name: &str, text: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, text).unwrap();
+    path
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn eval_flag_prints_the_result() {
+    let output = stutter(&["-e", "(+ 1 2)"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "3\n");
+}
+
+#[test]
+fn eval_flag_reports_errors_on_stderr() {
+    let output = stutter(&["-e", "(foo)"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("<expr>:1:1: error: 'foo' not in scope"));
+}
+
+#[test]
+fn truthiness_flag_selects_lisp_truthiness() {
+    let output = stutter(&["--truthiness", "lisp", "-e", "(if 0 \"yes\" 1)"]);
+    assert_eq!(stdout(&output), "\"yes\"\n");
+    let output = stutter(&["-e", "(if 0 \"yes\" 1)"]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = stutter(&["--truthiness", "fuzzy", "-e", "1"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn script_runs_top_to_bottom_with_arguments() {
+    let path = write_script(
+        "greet.lisp",
+        "(def greet (lambda (name) (print \"hello,\" name)))\n\
+         (map greet argv)\n",
+    );
+    let output =
+        stutter(&[path.to_str().unwrap(), "alice", "--", "-v", "bob"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "hello, alice\nhello, -v\nhello, bob\n");
+}
+
+#[test]
+fn failing_script_exits_with_status_one() {
+    let path = write_script("fail.lisp", "(print 1)\n(error \"boom\")\n");
+    let output = stutter(&[path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "1\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("fail.lisp:2:1: error: boom"));
+}
+
+#[test]
+fn missing_script_exits_with_status_two() {
+    let output = stutter(&["does-not-exist.lisp"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: could not read does-not-exist.lisp"));
+}
+
+#[test]
+fn stdlib_is_built_in() {
+    let output = Command::new(env!("CARGO_BIN_EXE_stutter"))
+        .args(["-e", "(sum (list 1 2 3))"])
+        .env_remove("STUTTER_PATH")
+        .env("HOME", env!("CARGO_TARGET_TMPDIR"))
+        .output()
+        .unwrap();
+    assert_eq!(stdout(&output), "6\n");
+}
+
+#[test]
+fn no_stdlib_starts_empty() {
+    let output = stutter(&["--no-stdlib", "-e", "(sum (list 1 2 3))"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("'sum' not in scope"));
+}
+
+#[test]
+fn stdlib_can_be_replaced() {
+    let path = write_script("mylib.lisp", "(def answer 42)\n");
+    let output =
+        stutter(&["--stdlib", path.to_str().unwrap(), "-e", "answer"]);
+    assert_eq!(stdout(&output), "42\n");
+}
+
+#[test]
+fn stutter_path_is_searched_for_the_stdlib() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("stutter-path");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("stdlib.lisp"), "(def answer 7)\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_stutter"))
+        .args(["-e", "answer"])
+        .env("STUTTER_PATH", &dir)
+        .output()
+        .unwrap();
+    assert_eq!(stdout(&output), "7\n");
+}
+
+#[test]
+fn missing_stdlib_is_a_clean_error() {
+    let output = stutter(&["--stdlib", "no-such-stdlib.lisp", "-e", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: could not read no-such-stdlib.lisp"));
+    assert!(!stderr.contains("panicked"));
+}
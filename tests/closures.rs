@@ -0,0 +1,77 @@
+mod common;
+
+use common::eval_last;
+
+#[test]
+fn returned_lambda_keeps_let_binding() {
+    assert_eq!(
+        eval_last("(let (f (let (n 5) (lambda (x) (+ x n)))) (f 1))"),
+        "6"
+    );
+}
+
+#[test]
+fn make_adder() {
+    let program = "
+        (def make-adder (lambda (n) (lambda (x) (+ x n))))
+        (let (add5 (make-adder 5)) (add5 10))
+    ";
+    assert_eq!(eval_last(program), "15");
+}
+
+#[test]
+fn compose_closures() {
+    let program = "
+        (def make-adder (lambda (n) (lambda (x) (+ x n))))
+        (def compose (lambda (f g) (lambda (x) (f (g x)))))
+        (let (h (compose (make-adder 1) (make-adder 10))) (h 0))
+    ";
+    assert_eq!(eval_last(program), "11");
+}
+
+#[test]
+fn closure_is_lexically_scoped() {
+    let program = "
+        (let (n 1)
+          (let (inner (lambda (y) (+ y n)))
+            (let (n 1000)
+              (inner 1))))
+    ";
+    assert_eq!(eval_last(program), "2");
+}
+
+#[test]
+fn curried_function() {
+    let program = "
+        (def curry (lambda (f) (lambda (x) (lambda (y) (f x y)))))
+        (let (add (curry (lambda (a b) (- a b))))
+          (let (ten-minus (add 10))
+            (ten-minus 3)))
+    ";
+    assert_eq!(eval_last(program), "7");
+}
+
+#[test]
+fn let_bound_lambda_can_recurse() {
+    let program = "
+        (let (count-down (lambda (n) (if (= n 0) 0 (count-down (- n 1)))))
+          (count-down 10))
+    ";
+    assert_eq!(eval_last(program), "0");
+}
+
+#[test]
+fn captured_names_win_over_the_bound_name() {
+    let program = "
+        (def (compose f g) (lambda (x) (f (g x))))
+        (def g (compose (lambda (x) (* x 10)) (lambda (x) (+ x 1))))
+        (g 1)
+    ";
+    assert_eq!(eval_last(program), "20");
+    let program = "
+        (def make (lambda (f) (lambda (x) (f x))))
+        (def f (make (lambda (y) (* y 2))))
+        (let (h (make (lambda (y) (+ y 1)))) (list (f 3) (h 3)))
+    ";
+    assert_eq!(eval_last(program), "(6 4)");
+}
@@ -0,0 +1,41 @@
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Feeds `input` to the stutter REPL in quiet mode, with the standard
+/// library loaded from this checkout, and returns everything the process
+/// did.
+pub fn run_stutter_output(input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stutter"))
+        .arg("--quiet")
+        .env("STUTTER_PATH", env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start stutter");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().expect("stutter did not exit")
+}
+
+/// Feeds `input` to the stutter REPL in quiet mode and returns one string
+/// per printed result.
+pub fn run_stutter(input: &str) -> Vec<String> {
+    let output = run_stutter_output(input);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Evaluates each line of `input` and returns the result of the last one.
+pub fn eval_last(input: &str) -> String {
+    run_stutter(input).pop().unwrap_or_default()
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn and_or_short_circuit() {
+    assert_eq!(
+        eval_last("(and false (error \"not evaluated\"))\n"),
+        "False"
+    );
+    assert_eq!(eval_last("(or true (error \"not evaluated\"))\n"), "True");
+    assert_eq!(eval_last("(and true true false true)\n"), "False");
+    assert_eq!(eval_last("(or false false true)\n"), "True");
+}
+
+#[test]
+fn and_or_with_no_arguments() {
+    assert_eq!(run_stutter("(and)\n(or)\n"), vec!["True", "False"]);
+}
+
+#[test]
+fn and_or_keep_the_last_expression_in_tail_position() {
+    let input = "(def count-down (lambda (n) \
+                 (or (= n 0) (and (> n 0) (count-down (- n 1))))))\n\
+                 (count-down 100000)\n";
+    assert_eq!(eval_last(input), "True");
+}
+
+#[test]
+fn not_is_a_first_class_builtin() {
+    assert_eq!(eval_last("(not (= 1 2))\n"), "True");
+    assert_eq!(eval_last("(map not (list true false))\n"), "(False True)");
+}
+
+#[test]
+fn cond_picks_the_first_true_clause() {
+    let input = "(def sign (lambda (x) (cond \
+                 ((< x 0) \"negative\") \
+                 ((= x 0) \"zero\") \
+                 (else \"positive\"))))\n\
+                 (map sign (list -4 0 9))\n";
+    assert_eq!(eval_last(input), "(\"negative\" \"zero\" \"positive\")");
+    assert_eq!(eval_last("(cond ((= 1 2) 1))\n"), "Nil");
+    assert_eq!(eval_last("(def t true)\n(cond (t 1) (else 2))\n"), "1");
+}
+
+#[test]
+fn cond_rejects_malformed_clauses() {
+    assert_eq!(
+        run_stutter("(cond 1)\n")[0],
+        "<stdin>:1:1: error: expecting clauses of form (TEST EXPR...)"
+    );
+}
+
+#[test]
+fn when_and_unless_run_their_bodies() {
+    assert_eq!(eval_last("(when (= 1 1) (print \"hi\") 2)\n"), "2");
+    assert_eq!(
+        eval_last("(when (= 1 2) (error \"not evaluated\"))\n"),
+        "Nil"
+    );
+    assert_eq!(eval_last("(unless (= 1 2) 3)\n"), "3");
+}
+
+#[test]
+fn case_compares_against_literals() {
+    let input = "(def kind (lambda (x) (case x \
+                 ((1 3 5) 'odd) \
+                 ((2 4) 'even) \
+                 (\"zero\" 'word) \
+                 (else 'unknown))))\n\
+                 (map kind (list 3 4 \"zero\" 9))\n";
+    assert_eq!(eval_last(input), "(odd even word unknown)");
+    assert_eq!(eval_last("(case 'b (a 1) (b 2))\n"), "2");
+}
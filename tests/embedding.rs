@@ -0,0 +1,163 @@
+use num_bigint::BigInt;
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use stutter::{
+    ErrorKind, FromStutter, Interpreter, StutterObject, Truthiness,
+};
+
+#[test]
+fn eval_str_returns_the_last_value() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter.eval_str("(def x 2) (* x 21)").unwrap();
+    assert_eq!(i64::from_stutter(&value).unwrap(), 42);
+}
+
+#[test]
+fn definitions_persist_between_evaluations() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str("(def square (lambda (x) (* x x)))")
+        .unwrap();
+    let value = interpreter.eval_str("(map square (range 0 4))").unwrap();
+    assert_eq!(Vec::<i64>::from_stutter(&value).unwrap(), vec![0, 1, 4, 9]);
+}
+
+#[test]
+fn nil_converts_to_an_empty_vec() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter.eval_str("nil").unwrap();
+    assert_eq!(Vec::<i64>::from_stutter(&value).unwrap(), Vec::<i64>::new());
+}
+
+#[test]
+fn define_and_get_convert_rust_values() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define("xs", vec![1.5, 2.5]).unwrap();
+    interpreter.define("flag", true).unwrap();
+    interpreter.define("big", BigInt::from(10).pow(30)).unwrap();
+    interpreter
+        .eval_str("(def total (sum xs)) (def next (+ big 1))")
+        .unwrap();
+    assert_eq!(interpreter.get::<f64>("total").unwrap(), 4.0);
+    assert!(interpreter.get::<bool>("flag").unwrap());
+    assert_eq!(
+        interpreter.get::<BigInt>("next").unwrap(),
+        BigInt::from(10).pow(30) + 1
+    );
+    assert_eq!(
+        interpreter.get::<StutterObject>("flag").unwrap(),
+        StutterObject::Bool(true)
+    );
+}
+
+#[test]
+fn get_reports_unbound_and_mistyped_names() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define("s", "text").unwrap();
+    let err = interpreter.get::<i64>("missing").unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::Unbound(String::from("missing")));
+    let err = interpreter.get::<i64>("s").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Type(_)));
+}
+
+#[test]
+fn load_file_evaluates_a_script() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("embed.lisp");
+    fs::write(&path, "(def greeting (str-cat \"hi \" \"there\"))\n").unwrap();
+    let mut interpreter = Interpreter::empty();
+    interpreter.load_file(&path).unwrap();
+    assert_eq!(interpreter.get::<String>("greeting").unwrap(), "hi there");
+
+    let err = interpreter.load_file("no-such-file.lisp").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Io(_)));
+}
+
+#[test]
+fn errors_carry_their_location() {
+    let mut interpreter = Interpreter::empty();
+    let err = interpreter.eval_str("(+ 1\n  (foo))").unwrap_err();
+    let span = err.span().unwrap();
+    assert_eq!((span.line(), span.col()), (2, 3));
+    assert_eq!(span.source().name, "<string>");
+}
+
+#[test]
+fn truthiness_policy_is_selectable() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.truthiness(), Truthiness::Strict);
+    let err = interpreter.eval_str("(if 0 1 2)").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Type(_)));
+
+    interpreter.set_truthiness(Truthiness::Lisp);
+    interpreter.eval_str("(def none (cond))").unwrap();
+    let value = interpreter
+        .eval_str(
+            "(list (if 0 1 2) (if none 1 2) (or none \"x\") (and 1 2) \
+             (cond (none 1) ((list) 2)) (not none) (any (list none 0)) \
+             (filter (lambda (x) x) (list 1 none false 2)))",
+        )
+        .unwrap();
+    assert_eq!(value.to_string(), "(1 2 \"x\" 2 2 True True (1 2))");
+}
+
+#[test]
+fn every_empty_list_has_the_same_truthiness() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_truthiness(Truthiness::Lisp);
+    let value = interpreter
+        .eval_str("(list (if (list) 1 2) (if (rest (list 1)) 1 2))")
+        .unwrap();
+    assert_eq!(value.to_string(), "(1 1)");
+}
+
+/// A writer whose output the test can still read once the interpreter
+/// holds it.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn print_goes_to_the_interpreters_output() {
+    let buffer = SharedBuffer::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(buffer.clone());
+    interpreter
+        .eval_str("(print \"hi\" 1)\n(map (lambda (x) (print x)) (list 2 3))")
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(buffer.0.borrow().clone()).unwrap(),
+        "hi 1\n2\n3\n"
+    );
+}
+
+struct BrokenPipe;
+
+impl Write for BrokenPipe {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn failing_to_print_is_an_io_error() {
+    let mut interpreter = Interpreter::empty();
+    interpreter.set_output(BrokenPipe);
+    let err = interpreter.eval_str("(print 1)").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Io(_)));
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::run_stutter;
+
+#[test]
+fn error_points_at_offending_token() {
+    let out = run_stutter("(+ 1\n   (foo 2))\n");
+    assert_eq!(
+        out,
+        vec![
+            "<stdin>:2:4: error: 'foo' not in scope",
+            "   (foo 2))",
+            "   ^",
+        ]
+    );
+}
+
+#[test]
+fn error_inside_lambda_points_at_its_definition() {
+    let out = run_stutter("(def f (lambda (x)\n  (+ x \"s\")))\n(f 1)\n");
+    assert_eq!(out[1], "<stdin>:2:3: error: incompatible types: (Add Int(1) Str(\"s\")) not supported");
+    assert_eq!(out[2], "  (+ x \"s\")))");
+    assert_eq!(out[3], "  ^");
+}
+
+#[test]
+fn syntax_errors_have_locations() {
+    assert_eq!(
+        run_stutter(")\n")[0],
+        "<stdin>:1:1: error: syntax error, unexpected ')'"
+    );
+    assert_eq!(
+        run_stutter("(list \"abc)\n")[0],
+        "<stdin>:1:7: error: unterminated string literal"
+    );
+}
+
+#[test]
+fn user_raised_error_has_backtrace() {
+    let program = "
+(def outer (lambda (x) (+ 1 (inner x))))
+(def inner (lambda (y) (error \"bad value:\" y)))
+(outer 7)
+";
+    let out = run_stutter(program);
+    let n = out.len();
+    assert_eq!(out[n - 5], "<stdin>:1:24: error: bad value: 7");
+    assert_eq!(out[n - 2], "  in inner");
+    assert_eq!(out[n - 1], "  in outer");
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn keywords_evaluate_to_themselves() {
+    assert_eq!(eval_last(":size\n"), ":size");
+    assert_eq!(
+        eval_last("(list :a (= :a :a) (= :a :b))\n"),
+        "(:a True False)"
+    );
+    assert_eq!(eval_last("'(:b 1)\n"), "(:b 1)");
+}
+
+#[test]
+fn keyword_params_take_defaults() {
+    let input = "(def (scale x &key (by 2) offset) (list (* x by) offset))\n";
+    assert_eq!(eval_last(&format!("{}(scale 5)\n", input)), "(10 Nil)");
+    assert_eq!(
+        eval_last(&format!("{}(scale 5 :offset 1 :by 3)\n", input)),
+        "(15 1)"
+    );
+}
+
+#[test]
+fn keyword_arguments_follow_positional_ones() {
+    let input = "(def (f a &optional (b 0) &rest more &key (k 1)) \
+                 (list a b more k))\n";
+    assert_eq!(
+        eval_last(&format!("{}(f 1 2 3 :k 4)\n", input)),
+        "(1 2 (3) 4)"
+    );
+    assert_eq!(
+        run_stutter(&format!("{}(f :k 4)\n", input))[1],
+        "<stdin>:1:1: error: f expecting at least 1 argument, got 0"
+    );
+}
+
+#[test]
+fn bad_keyword_arguments_are_errors() {
+    let input = "(def (f &key (k 1)) k)\n";
+    let out =
+        run_stutter(&format!("{}(f :j 2)\n(f :k)\n(f :k 1 :k 2)\n", input));
+    assert_eq!(out[1], "<stdin>:1:1: error: f got unknown keyword :j");
+    assert_eq!(out[4], "<stdin>:1:1: error: f expecting a value after :k");
+    assert_eq!(out[7], "<stdin>:1:1: error: f got :k more than once");
+}
+
+#[test]
+fn stdlib_step_sizes_can_be_overridden() {
+    let input = "(def d (deriv (lambda (x) (* x x)) 5.0 :h 1e-6))\n\
+                 (and (> d 9.9999) (< d 10.0001))\n";
+    assert_eq!(eval_last(input), "True");
+    assert_eq!(eval_last("(sigma 0 10 (lambda (x) x) :step 5)\n"), "5");
+    assert_eq!(eval_last("(range 10 0 :step -3)\n"), "(10 7 4 1)");
+}
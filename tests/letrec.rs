@@ -0,0 +1,85 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+use std::rc::Rc;
+use stutter::{Interpreter, StutterObject};
+
+const PARITY: &str = "(def (parity n) \
+    (letrec ((even? (lambda (n) (if (= n 0) true (odd? (- n 1))))) \
+             (odd? (lambda (n) (if (= n 0) false (even? (- n 1)))))) \
+      (list (even? n) (odd? n))))\n";
+
+#[test]
+fn letrec_lambdas_can_call_each_other() {
+    assert_eq!(
+        eval_last(&format!("{}(parity 7)\n", PARITY)),
+        "(False True)"
+    );
+    assert_eq!(
+        eval_last(&format!("{}(parity 10)\n", PARITY)),
+        "(True False)"
+    );
+}
+
+#[test]
+fn mutual_recursion_runs_in_constant_stack() {
+    assert_eq!(
+        eval_last(&format!("{}(parity 100001)\n", PARITY)),
+        "(False True)"
+    );
+}
+
+#[test]
+fn letrec_lambdas_keep_their_group_when_returned() {
+    let input =
+        "(def f (letrec ((a (lambda () (b))) (b (lambda () 42))) a))\n\
+                 (f)\n";
+    assert_eq!(eval_last(input), "42");
+}
+
+#[test]
+fn letrec_binds_in_order_like_let() {
+    let input = "(letrec (x 2) (sq (lambda (n) (* n n))) (sq x))\n";
+    assert_eq!(eval_last(input), "4");
+}
+
+#[test]
+fn letrec_lambdas_see_later_values() {
+    let input = "(letrec ((f (lambda () (* 2 y))) (y 5)) (f))\n";
+    assert_eq!(eval_last(input), "10");
+}
+
+#[test]
+fn let_lambdas_cannot_see_later_bindings() {
+    let out =
+        run_stutter("(let ((a (lambda () (b))) (b (lambda () 1))) (a))\n");
+    assert_eq!(out[0], "<stdin>:1:21: error: 'b' not in scope");
+}
+
+#[test]
+fn letrec_lambdas_keep_their_own_scope() {
+    let input = "(def (make-adder k) (lambda (x) (+ x k)))\n\
+                 (letrec ((add3 (make-adder 3))) (add3 1))\n";
+    assert_eq!(eval_last(input), "4");
+    let input =
+        "(letrec ((a 10) (f (let ((z 5)) (lambda () (+ z a))))) (f))\n";
+    assert_eq!(eval_last(input), "15");
+}
+
+#[test]
+fn letrec_scopes_are_freed() {
+    let probe = Rc::new(());
+    let held = probe.clone();
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .register_fn("probe", 0, move |_| {
+            let _ = &held;
+            Ok(StutterObject::Nil)
+        })
+        .unwrap();
+    interpreter
+        .eval_str("(letrec ((p probe) (f (lambda () (p)))) (f))")
+        .unwrap();
+    drop(interpreter);
+    assert_eq!(Rc::strong_count(&probe), 1);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn cons_first_and_rest() {
+    let input = "(list (cons 1 (cons 2 nil)) (cons 0 (list 1 2)) \
+                 (first (list 1 2)) (rest (list 1 2)) (rest (list 1)))\n";
+    assert_eq!(eval_last(input), "((1 2) (0 1 2) 1 (2) ())");
+}
+
+#[test]
+fn nil_is_the_empty_list() {
+    let input = "(list (first nil) (rest nil) (len nil) (= nil (list)) \
+                 (= (list 1) nil) (empty? nil) (list? nil))\n";
+    assert_eq!(eval_last(input), "(Nil () 0 True False True True)");
+}
+
+#[test]
+fn nil_works_wherever_a_list_does() {
+    let input = "(list (cat nil (list 1) nil) (append 1 nil) (take 0 nil) \
+                 (drop 0 nil) (join \",\" nil) (apply + 1 nil) (set nil) \
+                 `(1 ,@nil 2) (apply list (rest (list 1))))\n";
+    assert_eq!(eval_last(input), "((1) (1) () () \"\" 1 #{} (1 2) ())");
+    assert_eq!(
+        run_stutter("(index 0 nil)\n")[0],
+        "<stdin>:1:1: error: index: 0 out of range for list of length 0"
+    );
+}
+
+#[test]
+fn nil_and_the_empty_list_are_interchangeable() {
+    let input = "(list (= (list nil) (list (list))) (= {:a nil} {:a (list)}) \
+                 (get {nil 1} (list)) (member? (list) (set (list nil))) \
+                 (= (list (rest (list 1)) (drop 1 (list 1)) \
+                 (filter (lambda (x) false) (list 1))) (list nil nil nil)))\n";
+    assert_eq!(eval_last(input), "(True True 1 True True)");
+}
+
+#[test]
+fn list_predicates() {
+    let input = "(list (list? (list)) (list? 1) (empty? (list)) \
+                 (empty? (list 1)) (pair? (list 1)) (pair? (list)) (pair? nil))\n";
+    assert_eq!(eval_last(input), "(True False True False True False False)");
+}
+
+#[test]
+fn recursive_list_processing() {
+    let input = "(def (my-reverse l acc) \
+                 (if (empty? l) acc (my-reverse (rest l) (cons (first l) acc))))\n\
+                 (my-reverse (range 0 5) nil)\n\
+                 (len (my-reverse (range 0 50000) nil))\n";
+    assert_eq!(run_stutter(input)[1..], ["(4 3 2 1 0)", "50000"]);
+}
+
+#[test]
+fn cons_onto_a_non_list_is_an_error() {
+    assert_eq!(
+        run_stutter("(cons 1 2)\n")[0],
+        "<stdin>:1:1: error: type error: expected form (cons ITEM LIST), got 2"
+    );
+}
+
+#[test]
+fn list_builtin_names_can_be_variables() {
+    let input = "(def (f first rest) (cons first rest))\n\
+                 (list (f 1 (list 2)) (let (empty? 3) empty?))\n";
+    assert_eq!(eval_last(input), "((1 2) 3)");
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn quasiquote_builds_lists() {
+    assert_eq!(
+        eval_last("`(a b ,(+ 1 2) ,@(list 4 5) c)\n"),
+        "(a b 3 4 5 c)"
+    );
+    assert_eq!(
+        eval_last("`(1 \"s\" (nested ,(* 2 3)))\n"),
+        "(1 \"s\" (nested 6))"
+    );
+}
+
+#[test]
+fn nested_quasiquote_keeps_inner_unquotes() {
+    assert_eq!(
+        eval_last("``(a ,(b ,(+ 1 2)))\n"),
+        "(quasiquote (a (unquote (b 3))))"
+    );
+}
+
+#[test]
+fn defmacro_defines_a_control_form() {
+    let input = "(defmacro if-not (c x y) `(if ,c ,y ,x))\n\
+                 (if-not (= 1 2) \"yes\" (error \"not evaluated\"))\n";
+    assert_eq!(eval_last(input), "\"yes\"");
+}
+
+#[test]
+fn macros_expand_inside_lambda_bodies() {
+    let input = "(defmacro inc (x) `(+ 1 ,x))\n\
+                 (def f (lambda (n) (inc n)))\n\
+                 (f 41)\n";
+    assert_eq!(eval_last(input), "42");
+}
+
+#[test]
+fn macroexpand_shows_the_expansion() {
+    let input = "(defmacro if-not (c x y) `(if ,c ,y ,x))\n\
+                 (defmacro never (x) `(if-not true ,x 0))\n\
+                 (macroexpand-1 `(never 1))\n\
+                 (macroexpand `(never 1))\n\
+                 (macroexpand `(+ 1 2))\n";
+    let out = run_stutter(input);
+    assert_eq!(out[2], "(if-not True 1 0)");
+    assert_eq!(out[3], "(if True 0 1)");
+    assert_eq!(out[4], "(+ 1 2)");
+}
+
+#[test]
+fn gensym_avoids_capturing_user_names() {
+    let input = "(defmacro my-or (a b)\n\
+                 \x20 (let (t (gensym)) `(let (,t ,a) (if ,t ,t ,b))))\n\
+                 (let (t 5) (my-or false t))\n";
+    assert_eq!(eval_last(input), "5");
+}
+
+#[test]
+fn stdlib_and_or_short_circuit() {
+    assert_eq!(eval_last("(or true (error \"boom\"))\n"), "True");
+    assert_eq!(eval_last("(and false (error \"boom\"))\n"), "False");
+    assert_eq!(eval_last("(not (= 1 2))\n"), "True");
+}
+
+#[test]
+fn macro_errors() {
+    let out = run_stutter(",x\n(defmacro two (a b) a)\n(two 1)\n`(1 ,@2)\n");
+    assert_eq!(out[0], "<stdin>:1:1: error: unquote outside of quasiquote");
+    assert_eq!(
+        out[4],
+        "<stdin>:1:1: error: macro two expecting 2 arguments, got 1"
+    );
+    assert_eq!(out[7], "<stdin>:1:5: error: ',@' expecting a list, got 2");
+}
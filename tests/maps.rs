@@ -0,0 +1,87 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn map_literals_print_in_key_order() {
+    assert_eq!(eval_last("{:b 2 :a 1}\n"), "{:a 1 :b 2}");
+    assert_eq!(
+        eval_last("(hash-map \"x\" (+ 1 2) 7 true)\n"),
+        "{7 True \"x\" 3}"
+    );
+    assert_eq!(eval_last("{}\n"), "{}");
+}
+
+#[test]
+fn get_looks_up_keys() {
+    let input = "(def m {:a 1 (list 1 2) \"pair\"})\n";
+    assert_eq!(
+        run_stutter(&format!(
+            "{}(get m :a)\n(get m (list 1 2))\n(get m :z)\n(get m :z 0)\n",
+            input
+        ))[1..],
+        ["1", "\"pair\"", "Nil", "0"]
+    );
+}
+
+#[test]
+fn maps_are_persistent() {
+    let input = "(def m {:a 1})\n(def n (assoc m :b 2 :a 10))\n(list m n (dissoc n :a))\n";
+    assert_eq!(eval_last(input), "({:a 1} {:a 10 :b 2} {:b 2})");
+}
+
+#[test]
+fn keys_vals_and_contains() {
+    let input = "(def m {:b 2 :a 1})\n(list (keys m) (vals m) (contains? m :a) (contains? m :c) (len m))\n";
+    assert_eq!(eval_last(input), "((:a :b) (1 2) True False 2)");
+}
+
+#[test]
+fn merge_prefers_later_maps() {
+    assert_eq!(
+        eval_last("(merge {:a 1 :b 1} {:b 2} {:c 3})\n"),
+        "{:a 1 :b 2 :c 3}"
+    );
+}
+
+#[test]
+fn update_applies_a_function_to_a_value() {
+    assert_eq!(eval_last("(update {:n 1} :n + 10)\n"), "{:n 11}");
+    let input = "(def (count-words words) \
+                 (fold (lambda (counts w) (assoc counts w (+ 1 (get counts w 0)))) {} words))\n\
+                 (count-words (split \" \" \"a b a c a\"))\n";
+    assert_eq!(eval_last(input), "{\"a\" 3 \"b\" 1 \"c\" 1}");
+}
+
+#[test]
+fn maps_compare_by_value() {
+    assert_eq!(
+        eval_last("(= {:a (list 1 2)} (assoc {} :a (list 1 2)))\n"),
+        "True"
+    );
+    assert_eq!(eval_last("(= {:a 1} {:a 2})\n"), "False");
+}
+
+#[test]
+fn unhashable_keys_and_bad_literals_are_errors() {
+    assert_eq!(
+        run_stutter("{1.5 2}\n")[0],
+        "<stdin>:1:1: error: 1.5 cannot be used as a map key"
+    );
+    assert_eq!(
+        run_stutter("{:a}\n")[0],
+        "<stdin>:1:1: error: expecting form of (hash-map KEY VALUE...), got a key without a value"
+    );
+    assert_eq!(
+        run_stutter("(1 2}\n")[0],
+        "<stdin>:1:5: error: syntax error, unexpected '}'"
+    );
+}
+
+#[test]
+fn map_builtin_names_can_be_variables() {
+    let input = "(def (f keys get) (list keys get))\n\
+                 (list (f 1 2) ((lambda (merge) (+ merge 1)) 4) (let (update 3) update))\n";
+    assert_eq!(eval_last(input), "((1 2) 5 3)");
+    assert_eq!(eval_last("(eval '{:a (+ 1 2)})\n"), "{:a 3}");
+}
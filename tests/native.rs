@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use stutter::{
+    Arity, ErrorKind, FromStutter, Interpreter, IntoStutter, StutterObject,
+};
+
+fn interpreter_with_double() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .register_fn("double", 1, |args| {
+            Ok((i64::from_stutter(&args[0])? * 2).into_stutter())
+        })
+        .unwrap();
+    interpreter
+}
+
+#[test]
+fn native_function_is_callable_and_first_class() {
+    let mut interpreter = interpreter_with_double();
+    let value = interpreter.eval_str("(double 21)").unwrap();
+    assert_eq!(i64::from_stutter(&value).unwrap(), 42);
+    let value = interpreter.eval_str("(map double (range 0 3))").unwrap();
+    assert_eq!(Vec::<i64>::from_stutter(&value).unwrap(), vec![0, 2, 4]);
+    let value = interpreter.eval_str("double").unwrap();
+    assert_eq!(value.to_string(), "<native double>");
+}
+
+#[test]
+fn arity_is_checked_before_calling() {
+    let mut interpreter = interpreter_with_double();
+    let err = interpreter.eval_str("(double 1 2)").unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::Arity(String::from("double expecting 1 argument, got 2"))
+    );
+}
+
+#[test]
+fn variadic_native_function() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .register_fn("count-args", Arity::AtLeast(1), |args| {
+            Ok((args.len() as i64).into_stutter())
+        })
+        .unwrap();
+    let value = interpreter.eval_str("(count-args 1 2 3)").unwrap();
+    assert_eq!(i64::from_stutter(&value).unwrap(), 3);
+    let err = interpreter.eval_str("(count-args)").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Arity(_)));
+}
+
+#[test]
+fn native_errors_point_at_the_call() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .register_fn("fail", 0, |_| {
+            Err(ErrorKind::Value(String::from("no good")).into())
+        })
+        .unwrap();
+    let err = interpreter.eval_str("(+ 1\n  (fail))").unwrap_err();
+    assert_eq!(
+        err.render(),
+        "<string>:2:3: error: no good\n  (fail))\n  ^\n  in fail"
+    );
+}
+
+#[test]
+fn native_function_can_capture_host_state() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let sink = log.clone();
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .register_fn("emit", 1, move |args| {
+            sink.borrow_mut().push(args[0].clone());
+            Ok(StutterObject::Nil)
+        })
+        .unwrap();
+    interpreter.eval_str("(map emit (list 1 \"two\"))").unwrap();
+    assert_eq!(
+        *log.borrow(),
+        vec![1i64.into_stutter(), "two".into_stutter()]
+    );
+}
+
+#[test]
+fn native_functions_can_replace_library_builtins() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .register_fn("print", 1, |args| {
+            Ok(format!("printed {}", args[0]).into_stutter())
+        })
+        .unwrap();
+    let value = interpreter.eval_str("(print 1)").unwrap();
+    assert_eq!(String::from_stutter(&value).unwrap(), "printed 1");
+}
+
+#[test]
+fn reserved_names_cannot_be_defined() {
+    let mut interpreter = Interpreter::new();
+    let err = interpreter
+        .register_fn("if", 1, |_| Ok(StutterObject::Nil))
+        .unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &ErrorKind::Syntax(String::from(
+            "'if' is reserved and can't be defined"
+        ))
+    );
+    assert!(interpreter.define("+", 1i64).is_err());
+}
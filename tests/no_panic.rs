@@ -0,0 +1,284 @@
+mod common;
+
+use common::{run_stutter, run_stutter_output};
+use std::thread;
+use stutter::{ErrorKind, Interpreter};
+
+/// Evaluates `input` with a fresh interpreter on a thread with the default
+/// stack size, the way a host would.
+fn eval_on_thread(input: String) -> Result<String, ErrorKind> {
+    thread::spawn(move || {
+        Interpreter::new()
+            .eval_str(&input)
+            .map(|value| value.to_string())
+            .map_err(|e| e.kind().clone())
+    })
+    .join()
+    .expect("interpreter thread crashed")
+}
+
+/// Runs `input` and fails the test if the interpreter died instead of
+/// reporting an error.
+fn assert_survives(input: &str) {
+    let output = run_stutter_output(input);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success() && !stderr.contains("panicked"),
+        "interpreter crashed on {:?}:\n{}",
+        input,
+        stderr
+    );
+}
+
+#[test]
+fn out_of_range_list_access_is_an_error() {
+    let out = run_stutter(
+        "(index 5 (list 1))\n(take 5 (list 1))\n(drop 5 (list 1))\n",
+    );
+    assert_eq!(
+        out[0],
+        "<stdin>:1:1: error: index: 5 out of range for list of length 1"
+    );
+    assert_eq!(
+        out[3],
+        "<stdin>:1:1: error: take: 5 out of range for list of length 1"
+    );
+    assert_eq!(
+        out[6],
+        "<stdin>:1:1: error: drop: 5 out of range for list of length 1"
+    );
+}
+
+#[test]
+fn division_by_zero_is_an_error() {
+    let out = run_stutter("(// 1 0)\n(% 1 0)\n");
+    assert_eq!(out[0], "<stdin>:1:1: error: division by zero");
+    assert_eq!(out[3], "<stdin>:1:1: error: division by zero");
+}
+
+#[test]
+fn missing_arguments_are_errors() {
+    let out = run_stutter("(+)\n");
+    assert_eq!(
+        out[0],
+        "<stdin>:1:1: error: + expecting at least 1 argument, got 0"
+    );
+    for input in &["(let)\n", "(lambda)\n", "(index)\n", "(len)\n"] {
+        assert!(run_stutter(input)[0].contains("error"), "{}", input);
+    }
+}
+
+#[test]
+fn runaway_recursion_is_an_error() {
+    let out =
+        run_stutter("(def f (lambda (n) (+ 1 (f n))))\n(f 0)\n(+ 1 2)\n");
+    assert!(out[1].ends_with("maximum recursion depth of 10000 exceeded"));
+    assert_eq!(out[4], "  in f (9999 times)");
+    assert_eq!(out[5], "3");
+}
+
+#[test]
+fn deep_recursion_works_on_an_ordinary_thread() {
+    let runaway = "(def f (lambda (n) (+ 1 (f n))))\n(f 0)\n";
+    assert_eq!(
+        eval_on_thread(runaway.to_string()),
+        Err(ErrorKind::RecursionLimit)
+    );
+    let counted = "(def (f n) (if (= n 0) 0 (+ 1 (f (- n 1)))))\n(f 9000)\n";
+    assert_eq!(
+        eval_on_thread(counted.to_string()),
+        Ok(String::from("9000"))
+    );
+}
+
+#[test]
+fn deeply_nested_code_is_an_error() {
+    let depth = 100_000;
+    for (open, close) in &[("'(", ")"), ("(list ", ")"), ("`(", ")")] {
+        let input = format!("{}1{}", open.repeat(depth), close.repeat(depth));
+        match eval_on_thread(input) {
+            Err(ErrorKind::Parse(msg)) => assert_eq!(
+                msg,
+                "syntax error, expressions nested more than 1000 deep"
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn deeply_nested_data_cannot_become_code() {
+    let input = "(def (nest n acc) (if (= n 0) acc (nest (- n 1) (list 'list acc))))\n\
+                 (eval (nest 5000 1))\n";
+    assert_eq!(
+        eval_on_thread(input.to_string()),
+        Err(ErrorKind::Syntax(String::from(
+            "expressions nested more than 1000 deep"
+        )))
+    );
+}
+
+const NEST: &str =
+    "(def (nest n acc) (if (= n 0) acc (nest (- n 1) (list acc))))\n";
+
+#[test]
+fn deeply_nested_data_can_be_dropped_and_compared() {
+    let input = format!("{}(len (nest 100000 1))\n", NEST);
+    assert_eq!(eval_on_thread(input), Ok(String::from("1")));
+    let input = format!(
+        "{}(def big (nest 50000 1))\n(list (= big big) (= big (nest 50000 2)))\n",
+        NEST
+    );
+    assert_eq!(eval_on_thread(input), Ok(String::from("(True False)")));
+    let input = format!("{}(+ (nest 50000 1) 1)\n", NEST);
+    assert!(matches!(eval_on_thread(input), Err(ErrorKind::Type(_))));
+}
+
+#[test]
+fn deeply_nested_keys_are_an_error() {
+    let input = format!("{}(set (list (nest 5000 1)))\n", NEST);
+    assert_eq!(
+        eval_on_thread(input),
+        Err(ErrorKind::Value(String::from(
+            "map keys nested more than 1000 deep"
+        )))
+    );
+}
+
+/// A small xorshift generator so the fuzz inputs are the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn pick<'a>(&mut self, choices: &[&'a str]) -> &'a str {
+        choices[(self.next() % choices.len() as u64) as usize]
+    }
+}
+
+const WORDS: &[&str] = &[
+    "(",
+    "(",
+    "(",
+    ")",
+    ")",
+    ")",
+    "+",
+    "-",
+    "*",
+    "/",
+    "//",
+    "%",
+    "<",
+    ">",
+    "=",
+    ">=",
+    "<=",
+    "let",
+    "lambda",
+    "list",
+    "take",
+    "if",
+    "and",
+    "or",
+    "not",
+    "cond",
+    "case",
+    "else",
+    "when",
+    "begin",
+    "letrec",
+    "{",
+    "}",
+    "get",
+    "assoc",
+    "update",
+    "keys",
+    ":a",
+    "apply",
+    "&rest",
+    "&optional",
+    "&key",
+    ":h",
+    "index",
+    "drop",
+    "quote",
+    "append",
+    "range",
+    "cat",
+    "len",
+    "real",
+    "int",
+    "str-len",
+    "substr",
+    "str-cat",
+    "split",
+    "join",
+    "upper",
+    "lower",
+    "trim",
+    "contains",
+    "starts-with",
+    "to-string",
+    "parse-int",
+    "error",
+    "map",
+    "filter",
+    "fold",
+    "cons",
+    "first",
+    "rest",
+    "nil",
+    "empty?",
+    "#{",
+    "union",
+    "member?",
+    "set",
+    "x",
+    "y",
+    "0",
+    "1",
+    "2",
+    "-1",
+    "3.5",
+    "1/3",
+    "numerator",
+    "0.0",
+    "99999999999999999999999",
+    "\"\"",
+    "\"a b\"",
+    "\"",
+    "true",
+    "false",
+    "`",
+    ",",
+    ",@",
+    "'",
+    "eval",
+    "gensym",
+    "macroexpand",
+    ";",
+    "\n",
+];
+
+#[test]
+fn random_token_soup_never_crashes() {
+    let mut rng = Rng(0x5eed_cafe_f00d);
+    for _ in 0..20 {
+        let mut input = String::new();
+        for _ in 0..50 {
+            let len = 1 + rng.next() % 20;
+            for _ in 0..len {
+                input.push_str(rng.pick(WORDS));
+                input.push(' ');
+            }
+            input.push('\n');
+        }
+        assert_survives(&input);
+    }
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn calls_with_the_wrong_number_of_arguments_are_errors() {
+    let out = run_stutter("(def f (lambda (a b) a))\n(f 1)\n(f 1 2 3)\n");
+    assert_eq!(out[1], "<stdin>:1:1: error: f expecting 2 arguments, got 1");
+    assert_eq!(out[4], "<stdin>:1:1: error: f expecting 2 arguments, got 3");
+    assert_eq!(
+        run_stutter("((lambda (x) x))\n")[0],
+        "<stdin>:1:1: error: <lambda> expecting 1 argument, got 0"
+    );
+}
+
+#[test]
+fn rest_params_collect_extra_arguments() {
+    let input = "(def f (lambda (a &rest more) (list a more)))\n";
+    assert_eq!(eval_last(&format!("{}(f 1 2 3)\n", input)), "(1 (2 3))");
+    assert_eq!(eval_last(&format!("{}(f 1)\n", input)), "(1 ())");
+    assert_eq!(
+        run_stutter(&format!("{}(f)\n", input))[1],
+        "<stdin>:1:1: error: f expecting at least 1 argument, got 0"
+    );
+}
+
+#[test]
+fn optional_params_take_defaults() {
+    let input = "(def (f a &optional (b (* a 10)) c) (list a b c))\n";
+    assert_eq!(eval_last(&format!("{}(f 1)\n", input)), "(1 10 Nil)");
+    assert_eq!(eval_last(&format!("{}(f 1 2 3)\n", input)), "(1 2 3)");
+    assert_eq!(
+        run_stutter(&format!("{}(f 1 2 3 4)\n", input))[1],
+        "<stdin>:1:1: error: f expecting 1 to 3 arguments, got 4"
+    );
+    assert_eq!(
+        run_stutter("(def (g &optional a) a)\n(g 1 2)\n")[1],
+        "<stdin>:1:1: error: g expecting 0 to 1 arguments, got 2"
+    );
+}
+
+#[test]
+fn malformed_param_lists_are_syntax_errors() {
+    assert_eq!(
+        run_stutter("(lambda (a &rest) a)\n")[0],
+        "<stdin>:1:1: error: expecting one param after &rest"
+    );
+    assert_eq!(
+        run_stutter("(lambda (a (b 1)) a)\n")[0],
+        "<stdin>:1:1: error: only &optional and &key params can have a default"
+    );
+}
+
+#[test]
+fn apply_spreads_a_list_into_arguments() {
+    assert_eq!(eval_last("(apply + (list 1 2 3))\n"), "6");
+    assert_eq!(eval_last("(apply list 1 2 (list 3 4))\n"), "(1 2 3 4)");
+    assert_eq!(
+        eval_last(
+            "(map (lambda (l) (apply * l)) (list (list 2 3) (list 4)))\n"
+        ),
+        "(6 4)"
+    );
+    assert_eq!(
+        run_stutter("(apply + 1)\n")[0],
+        "<stdin>:1:1: error: apply expecting a list as its last argument, got 1"
+    );
+}
+
+#[test]
+fn max_and_min_take_arguments_or_a_list() {
+    assert_eq!(eval_last("(max 3 9 2)\n"), "9");
+    assert_eq!(eval_last("(max (list 3 9 2))\n"), "9");
+    assert_eq!(eval_last("(min 4 1.5)\n"), "1.5");
+    assert_eq!(eval_last("(apply min (range 5 10))\n"), "5");
+}
+
+#[test]
+fn macros_take_rest_params() {
+    let input =
+        "(defmacro my-list (first &rest more) `(list ,first ,@more))\n\
+                 (my-list 1 (+ 1 1) 3)\n";
+    assert_eq!(eval_last(input), "(1 2 3)");
+}
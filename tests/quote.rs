@@ -0,0 +1,47 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn quote_returns_data() {
+    let out = run_stutter("(quote (+ 1 2))\n'(+ 1 2)\n'x\n'()\n''a\n");
+    assert_eq!(out, vec!["(+ 1 2)", "(+ 1 2)", "x", "()", "(quote a)"]);
+}
+
+#[test]
+fn quoted_lists_can_be_taken_apart() {
+    assert_eq!(eval_last("(index 0 '(if a b c))\n"), "if");
+    assert_eq!(eval_last("(len '(1 \"two\" (3 4)))\n"), "3");
+    assert_eq!(eval_last("(= 'a (index 1 '(b a)))\n"), "True");
+}
+
+#[test]
+fn eval_turns_data_back_into_code() {
+    assert_eq!(eval_last("(eval '(+ 1 2))\n"), "3");
+    assert_eq!(eval_last("(eval (list '* 6 7))\n"), "42");
+    let input = "(def code '(map (lambda (x) (* x x)) (range 0 4)))\n\
+                 (eval code)\n";
+    assert_eq!(eval_last(input), "(0 1 4 9)");
+    assert_eq!(eval_last("(map eval '((+ 1 1) (* 2 3)))\n"), "(2 6)");
+}
+
+#[test]
+fn quoted_code_evaluates_like_the_original() {
+    for code in ["(:a {:a 5})", "(nil)", "(1/2 3)", "({:k 1} :k)"] {
+        let direct = eval_last(&format!("{}\n", code));
+        let quoted = eval_last(&format!("(eval '{})\n", code));
+        assert_eq!(direct, quoted, "{}", code);
+    }
+}
+
+#[test]
+fn eval_runs_in_the_global_scope() {
+    let out = run_stutter("(def y 1)\n(let (y 3) (eval 'y))\n");
+    assert_eq!(out[1], "1");
+}
+
+#[test]
+fn errors_in_evaluated_code_point_at_the_eval() {
+    let out = run_stutter("(+ 1 (eval '(foo)))\n");
+    assert_eq!(out[0], "<stdin>:1:6: error: 'foo' not in scope");
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn integer_division_is_exact() {
+    assert_eq!(
+        eval_last("(list (/ 1 3) (/ 6 3) (/ 1 2 2) (/ -4 6))\n"),
+        "(1/3 2 1/4 -2/3)"
+    );
+    assert_eq!(eval_last("(/ (factorial 200) (factorial 199))\n"), "200");
+}
+
+#[test]
+fn ratio_literals_are_read_in_lowest_terms() {
+    assert_eq!(
+        eval_last("(list 2/3 4/2 -3/9 (+ 1/2 1/3) (* 2/3 3/2))\n"),
+        "(2/3 2 -1/3 5/6 1)"
+    );
+}
+
+#[test]
+fn numerator_and_denominator() {
+    assert_eq!(
+        eval_last(
+            "(list (numerator 6/4) (denominator 6/4) (numerator 5) (denominator 5))\n"
+        ),
+        "(3 2 5 1)"
+    );
+}
+
+#[test]
+fn numerator_and_denominator_can_be_variables() {
+    let input =
+        "(def (ratio numerator denominator) (/ numerator denominator))\n\
+                 (ratio 2 4)\n";
+    assert_eq!(eval_last(input), "1/2");
+}
+
+#[test]
+fn ratios_mix_with_other_numbers() {
+    let input = "(list (< 1/3 0.5) (+ 1/2 0.25) (= 1/2 (/ 2 4)) (pow 2/3 2) \
+                 (real 1/4) (int 7/2) (// 7/2 1) (% 7/2 1))\n";
+    assert_eq!(eval_last(input), "(True 0.75 True 4/9 0.25 3 3 1/2)");
+}
+
+#[test]
+fn negative_powers_are_exact() {
+    assert_eq!(
+        eval_last("(list (pow 2/3 -1) (pow 2 -1) (pow -2 -3) (pow 1/2 0))\n"),
+        "(3/2 1/2 -1/8 1)"
+    );
+    assert_eq!(
+        run_stutter("(pow 0 -1)\n")[0],
+        "<stdin>:1:1: error: division by zero"
+    );
+}
+
+#[test]
+fn division_by_zero_and_bad_arguments_are_errors() {
+    let out = run_stutter("(/ 1 0)\n(/ 1/2 0)\n(numerator 0.5)\n");
+    assert_eq!(out[0], "<stdin>:1:1: error: division by zero");
+    assert_eq!(out[3], "<stdin>:1:1: error: division by zero");
+    assert_eq!(
+        out[6],
+        "<stdin>:1:1: error: type error: expected form (numerator RATIO), got 0.5"
+    );
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn set_literals_print_in_sorted_order() {
+    assert_eq!(eval_last("#{3 1 :a 2 1}\n"), "#{1 2 3 :a}");
+    assert_eq!(
+        eval_last("(set (list \"b\" \"a\" \"b\"))\n"),
+        "#{\"a\" \"b\"}"
+    );
+    assert_eq!(eval_last("#{}\n"), "#{}");
+}
+
+#[test]
+fn set_algebra() {
+    let input = "(def a #{1 2 3})\n(def b #{2 3 4})\n\
+                 (list (union a b) (intersection a b) (difference a b) \
+                 (union) (difference a b #{1}))\n";
+    assert_eq!(eval_last(input), "(#{1 2 3 4} #{2 3} #{1} #{} #{})");
+}
+
+#[test]
+fn membership_and_subsets() {
+    let input = "(def s #{1 (list 2 3)})\n\
+                 (list (member? 1 s) (member? (list 2 3) s) (member? 4 s) \
+                 (contains? s 1) (subset? #{1} s) (subset? s #{1}) (len s))\n";
+    assert_eq!(eval_last(input), "(True True False True True False 2)");
+}
+
+#[test]
+fn sets_compare_by_value_and_spread_in_order() {
+    assert_eq!(eval_last("(= #{1 2} (set (list 2 1 2)))\n"), "True");
+    assert_eq!(eval_last("(apply + #{1 2 3})\n"), "6");
+    assert_eq!(eval_last("(apply list #{3 1 2})\n"), "(1 2 3)");
+}
+
+#[test]
+fn bad_members_and_literals_are_errors() {
+    assert_eq!(
+        run_stutter("#{1.5}\n")[0],
+        "<stdin>:1:1: error: 1.5 cannot be used as a map key"
+    );
+    assert_eq!(
+        run_stutter("(union #{1} (list 2))\n")[0],
+        "<stdin>:1:1: error: type error: expected form (union SET...), got (2)"
+    );
+    assert_eq!(
+        run_stutter("#{1 2\n")[0],
+        "<stdin>:1:1: error: syntax error, unmatched '#{'"
+    );
+}
+
+#[test]
+fn set_builtin_names_can_be_variables() {
+    let input = "(def (f union difference) (list union difference))\n\
+                 (list (f 1 2) ((lambda (member?) member?) 4) (let (set 3) set))\n";
+    assert_eq!(eval_last(input), "((1 2) 4 3)");
+    assert_eq!(eval_last("(eval '#{(+ 1 2)})\n"), "#{3}");
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use common::{eval_last, run_stutter};
+
+#[test]
+fn escape_sequences_round_trip_through_printing() {
+    assert_eq!(
+        eval_last("\"tab\\there \\\"quoted\\\" back\\\\slash\\nnext\"\n"),
+        "\"tab\\there \\\"quoted\\\" back\\\\slash\\nnext\""
+    );
+    assert_eq!(eval_last("(str-len \"a\\tb\\n\\r\\0\")\n"), "6");
+}
+
+#[test]
+fn print_writes_strings_raw() {
+    assert_eq!(
+        run_stutter("(print \"say \\\"hi\\\"\\nbye\")\n"),
+        ["say \"hi\"", "bye", "Nil"]
+    );
+}
+
+#[test]
+fn bad_string_literals_are_lex_errors() {
+    assert_eq!(
+        run_stutter("\"bad \\q escape\"\n")[0],
+        "<stdin>:1:1: error: invalid escape sequence in string: \\q"
+    );
+    assert_eq!(
+        run_stutter("\"unterminated\n")[0],
+        "<stdin>:1:1: error: unterminated string literal"
+    );
+}
+
+#[test]
+fn lengths_and_substrings_count_characters() {
+    assert_eq!(eval_last("(str-len \"héllo→\")\n"), "6");
+    assert_eq!(
+        eval_last("(list (substr 1 4 \"héllo→\") (substr 4 6 \"héllo→\"))\n"),
+        "(\"éll\" \"o→\")"
+    );
+    let out = run_stutter("(substr 2 7 \"héllo→\")\n(substr 3 2 \"abc\")\n");
+    assert_eq!(
+        out[0],
+        "<stdin>:1:1: error: substr: range 2..7 out of bounds for string \
+         of length 6"
+    );
+    assert_eq!(
+        out[3],
+        "<stdin>:1:1: error: substr: range 3..2 out of bounds for string \
+         of length 3"
+    );
+}
+
+#[test]
+fn split_and_join() {
+    assert_eq!(
+        eval_last("(split \", \" \"a, b, c\")\n"),
+        "(\"a\" \"b\" \"c\")"
+    );
+    assert_eq!(eval_last("(split \"\" \"añb\")\n"), "(\"a\" \"ñ\" \"b\")");
+    assert_eq!(eval_last("(split \",\" \"\")\n"), "(\"\")");
+    assert_eq!(
+        eval_last("(join \"-\" (list \"a\" \"b\" \"c\"))\n"),
+        "\"a-b-c\""
+    );
+    assert_eq!(eval_last("(join \"-\" (list))\n"), "\"\"");
+    assert_eq!(
+        run_stutter("(join \"-\" (list 1 2))\n")[0],
+        "<stdin>:1:1: error: type error: expected form (join SEPARATOR LIST), \
+         got 1"
+    );
+}
+
+#[test]
+fn case_and_whitespace() {
+    assert_eq!(
+        eval_last(
+            "(list (upper \"straße\") (lower \"ÀBC\") (trim \"  hi \\n\"))\n"
+        ),
+        "(\"STRASSE\" \"àbc\" \"hi\")"
+    );
+    assert_eq!(eval_last("(str-cat \"a\" \"b\" \"c\")\n"), "\"abc\"");
+}
+
+#[test]
+fn searching() {
+    let input = "(list (contains \"ell\" \"hello\") (contains \"x\" \"hello\") \
+                 (starts-with \"he\" \"hello\") (starts-with \"lo\" \"hello\"))\n";
+    assert_eq!(eval_last(input), "(True False True False)");
+}
+
+#[test]
+fn converting_to_and_from_strings() {
+    assert_eq!(
+        eval_last(
+            "(list (to-string 42) (to-string \"s\") (to-string (list 1 \"a\" :k)) \
+             (to-string 1/2))\n"
+        ),
+        "(\"42\" \"s\" \"(1 \\\"a\\\" :k)\" \"1/2\")"
+    );
+    assert_eq!(eval_last("(parse-int \" 42 \")\n"), "42");
+    assert_eq!(
+        run_stutter("(parse-int \"4x2\")\n")[0],
+        "<stdin>:1:1: error: parse-int: invalid integer: \"4x2\""
+    );
+}
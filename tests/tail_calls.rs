@@ -0,0 +1,37 @@
+mod common;
+
+use common::eval_last;
+
+#[test]
+fn global_tail_recursion_runs_in_constant_stack() {
+    let program = "
+        (def count (lambda (n acc) (if (= n 0) acc (count (- n 1) (+ acc 1)))))
+        (count 100000 0)
+    ";
+    assert_eq!(eval_last(program), "100000");
+}
+
+#[test]
+fn let_bound_tail_recursion_runs_in_constant_stack() {
+    let program = "
+        (let (loop (lambda (n) (if (= n 0) 42 (loop (- n 1)))))
+          (loop 100000))
+    ";
+    assert_eq!(eval_last(program), "42");
+}
+
+#[test]
+fn tail_call_through_let_body() {
+    let program = "
+        (def down (lambda (n)
+          (let (m (- n 1))
+            (if (> m 0) (down m) m))))
+        (down 100000)
+    ";
+    assert_eq!(eval_last(program), "0");
+}
+
+#[test]
+fn stdlib_loop_over_many_iterations() {
+    assert_eq!(eval_last("(isprime 1000000007)"), "True");
+}